@@ -0,0 +1,250 @@
+//! Types exchanged between sedidata-server and sedidata-tui
+
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Riot ID with the capitalization chosen by the player, displayed as `name#tagline`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RiotId {
+    pub game_name: String,
+    pub tag_line: String,
+}
+
+impl RiotId {
+    /// Riot IDs are case-insensitive, so `Faker#KR1` matches `faker#kr1`
+    pub fn matches(&self, name: &str, tagline: &str) -> bool {
+        normalize_riot_id(&self.game_name, &self.tag_line) == normalize_riot_id(name, tagline)
+    }
+}
+
+impl fmt::Display for RiotId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.game_name, self.tag_line)
+    }
+}
+
+/// Canonical form of a Riot ID used for comparisons and cache keys
+pub fn normalize_riot_id(name: &str, tagline: &str) -> (String, String) {
+    (name.trim().to_lowercase(), tagline.trim().to_lowercase())
+}
+
+/// Age of a cached value, `stale` if it is past its TTL and could not be refreshed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Freshness {
+    pub cached_at: DateTime<Utc>,
+    /// Seconds since `cached_at` when the response was built, so clients need no clock of their own
+    pub age_secs: i64,
+    pub stale: bool,
+    /// Riot had no data (unranked, champion never played), such results are kept for a shorter time
+    #[serde(default)]
+    pub negative: bool,
+}
+
+impl Freshness {
+    pub fn fresh(cached_at: DateTime<Utc>) -> Self {
+        Self::new(cached_at, false)
+    }
+
+    pub fn stale(cached_at: DateTime<Utc>) -> Self {
+        Self::new(cached_at, true)
+    }
+
+    fn new(cached_at: DateTime<Utc>, stale: bool) -> Self {
+        let age_secs = (Utc::now() - cached_at).num_seconds().max(0);
+        Self {
+            cached_at,
+            age_secs,
+            stale,
+            negative: false,
+        }
+    }
+}
+
+/// Freshness of each part of the combined league response
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LeagueFreshness {
+    pub level: Freshness,
+    pub ranked_stats: Freshness,
+    pub champion_mastery: Option<Freshness>,
+}
+
+/// Ranked standing in one queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RankedStats {
+    /// e.g. `RANKED_SOLO_5x5` or `RANKED_FLEX_SR`
+    pub queue_type: String,
+    /// e.g. `GOLD`, empty while unranked
+    pub tier: String,
+    /// Division within the tier, `I` to `IV`
+    pub rank: String,
+    pub league_points: i32,
+    pub wins: i32,
+    pub losses: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChampionMasteryStats {
+    pub level: u64,
+    pub points: u64,
+}
+
+/// Response of `GET /v2/league`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LeagueResponse {
+    pub riot_id: RiotId,
+    pub level: u64,
+    pub ranked_stats: Vec<RankedStats>,
+    /// Only present if a champion was requested
+    pub champion_mastery: Option<ChampionMasteryStats>,
+    pub freshness: LeagueFreshness,
+}
+
+/// One player of `POST /v2/league/batch`, in request order, with either `data` or `error`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LeagueBatchEntry {
+    pub name: String,
+    pub tagline: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<LeagueResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<BatchPlayerError>,
+}
+
+/// Challenge points, in total or within one category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChallengePoints {
+    /// e.g. `GOLD`, `NONE` before the first level
+    pub level: String,
+    pub current: u64,
+    pub max: u64,
+    /// Share of players with at least this many points, from 0 to 1
+    pub percentile: Option<f64>,
+}
+
+/// Progress of a player in one challenge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PlayerChallenge {
+    pub challenge_id: u64,
+    pub level: String,
+    pub value: f64,
+    /// Share of players with at least this value, from 0 to 1
+    pub percentile: Option<f64>,
+    /// When the current level was reached
+    pub achieved_at: Option<DateTime<Utc>>,
+}
+
+/// Response of `GET /v2/challenges`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChallengesResponse {
+    pub riot_id: RiotId,
+    pub total_points: ChallengePoints,
+    /// Points by category, e.g. `COLLECTION` or `TEAMWORK`
+    pub category_points: BTreeMap<String, ChallengePoints>,
+    pub challenges: Vec<PlayerChallenge>,
+    pub freshness: Freshness,
+}
+
+/// One player of `POST /v2/challenges/batch`, in request order, with either `data` or `error`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChallengesBatchEntry {
+    pub name: String,
+    pub tagline: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<ChallengesResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<BatchPlayerError>,
+}
+
+/// Response of `GET /v2/challenges/percentiles`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChallengePercentilesResponse {
+    /// Share of players that reached each level (e.g. `GOLD`), by challenge id
+    pub percentiles: BTreeMap<u64, BTreeMap<String, f64>>,
+    pub freshness: Freshness,
+}
+
+/// Body of every error response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ErrorResponse {
+    /// Human readable description
+    pub error: String,
+    /// Stable machine readable code, e.g. `account_not_found`
+    pub code: String,
+    /// Set if the error was cached, e.g. an unknown Riot ID, and the Riot API was not asked again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<DateTime<Utc>>,
+}
+
+/// Error of a single player within a batch, the batch itself succeeds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BatchPlayerError {
+    /// HTTP status the error would have as a single request
+    pub status: u16,
+    pub code: String,
+    pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<DateTime<Utc>>,
+}
+
+/// Body of `GET /ready`, answered with 200 if `ready` and 503 otherwise
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReadyResponse {
+    /// Lookups can be answered with fresh data
+    pub ready: bool,
+    pub riot_api_key: KeyStatus,
+    pub upstream: UpstreamStatus,
+    pub cache: CacheStatus,
+    /// Time of the last check against the Riot API, `None` if it never finished
+    pub checked_at: Option<DateTime<Utc>>,
+    /// Riot API keys that were not rejected, requests are spread over them
+    #[serde(default)]
+    pub active_keys: usize,
+    #[serde(default)]
+    pub total_keys: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStatus {
+    /// Not checked yet, or the Riot API could not be reached
+    Unknown,
+    Valid,
+    /// Rejected by the Riot API, development keys expire after 24 hours
+    Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamStatus {
+    Unknown,
+    Reachable,
+    Unreachable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    Ok,
+    /// The cache database cannot be read, lookups still work but every one calls the Riot API
+    Failing,
+}
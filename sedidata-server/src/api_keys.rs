@@ -0,0 +1,174 @@
+use std::{
+    env, io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use crate::{config::Config, rate_limiter::RateLimiter};
+
+/// Environment variable with one or more comma separated keys, used without a key file
+const RIOT_API_KEY_ENV: &str = "RIOT_API_KEY";
+
+/// A Riot API key with its own rate limit accounting, Riot counts limits per key
+#[derive(Debug)]
+pub struct ApiKey {
+    secret: String,
+    limiter: RateLimiter,
+    rejected: AtomicBool,
+}
+
+impl ApiKey {
+    fn new(secret: String) -> Self {
+        Self {
+            secret,
+            limiter: RateLimiter::new(),
+            rejected: AtomicBool::new(false),
+        }
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    pub fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+
+    /// Last characters of the key, enough to tell keys apart in logs without leaking them
+    pub fn label(&self) -> String {
+        let tail: Vec<char> = self.secret.chars().rev().take(4).collect();
+        format!("...{}", tail.into_iter().rev().collect::<String>())
+    }
+
+    pub fn is_rejected(&self) -> bool {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Takes the key out of rotation, returns whether it was in rotation before
+    pub fn reject(&self) -> bool {
+        !self.rejected.swap(true, Ordering::Relaxed)
+    }
+
+    /// Puts the key back into rotation, returns whether it was rejected before
+    pub fn restore(&self) -> bool {
+        self.rejected.swap(false, Ordering::Relaxed)
+    }
+}
+
+/// Keys added and removed by `ApiKeyPool::replace`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChanges {
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// The configured Riot API keys, requests are spread over those that were not rejected
+#[derive(Debug, Default)]
+pub struct ApiKeyPool {
+    keys: RwLock<Vec<Arc<ApiKey>>>,
+    next: AtomicUsize,
+}
+
+impl ApiKeyPool {
+    pub fn new(secrets: Vec<String>) -> Self {
+        let pool = Self::default();
+        pool.replace(secrets);
+        pool
+    }
+
+    /// Swaps in a new set of keys. Keys that stay keep their rate limit state and rejection
+    pub fn replace(&self, secrets: Vec<String>) -> KeyChanges {
+        let mut keys = self.keys.write().unwrap();
+        let mut replaced: Vec<Arc<ApiKey>> = Vec::new();
+        let mut added = 0;
+        for secret in secrets {
+            if replaced.iter().any(|key| key.secret == secret) {
+                continue;
+            }
+            match keys.iter().find(|key| key.secret == secret) {
+                Some(key) => replaced.push(Arc::clone(key)),
+                None => {
+                    added += 1;
+                    replaced.push(Arc::new(ApiKey::new(secret)));
+                }
+            }
+        }
+
+        let removed = keys.len() - (replaced.len() - added);
+        *keys = replaced;
+        KeyChanges { added, removed }
+    }
+
+    pub fn all(&self) -> Vec<Arc<ApiKey>> {
+        self.keys.read().unwrap().clone()
+    }
+
+    /// Keys in rotation, starting with the next one in turn so that traffic is spread evenly
+    pub fn rotation(&self) -> Vec<Arc<ApiKey>> {
+        let mut active: Vec<_> = self
+            .keys
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|key| !key.is_rejected())
+            .cloned()
+            .collect();
+        if !active.is_empty() {
+            let start = self.next.fetch_add(1, Ordering::Relaxed) % active.len();
+            active.rotate_left(start);
+        }
+        active
+    }
+
+    /// Number of keys in rotation and of all keys
+    pub fn counts(&self) -> (usize, usize) {
+        let keys = self.keys.read().unwrap();
+        let active = keys.iter().filter(|key| !key.is_rejected()).count();
+        (active, keys.len())
+    }
+}
+
+/// Keys from the key file if one is configured, otherwise from `RIOT_API_KEY`
+pub fn load_keys(config: &Config) -> Result<Vec<String>, String> {
+    match &config.riot_api_keys_file {
+        Some(path) => {
+            read_key_file(path).map_err(|e| format!("Failed to read Riot API keys from {}: {}", path.display(), e))
+        }
+        None => {
+            let keys = env::var(RIOT_API_KEY_ENV)
+                .map(|value| parse_keys(&value))
+                .unwrap_or_default();
+            if keys.is_empty() {
+                return Err(format!("{} environment variable must be set", RIOT_API_KEY_ENV));
+            }
+            Ok(keys)
+        }
+    }
+}
+
+/// Splits a comma separated list of keys
+fn parse_keys(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reads one key per line, blank lines and lines starting with `#` are ignored
+pub fn read_key_file(path: &Path) -> io::Result<Vec<String>> {
+    let keys: Vec<String> = std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if keys.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "file contains no keys"));
+    }
+    Ok(keys)
+}
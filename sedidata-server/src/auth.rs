@@ -0,0 +1,97 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{config::ClientToken, error::ServerError};
+
+const QUOTA_WINDOW: Duration = Duration::from_secs(60);
+
+/// Optional token authentication with a per-client request quota, disabled if no tokens are configured.
+/// The admin token is separate and has no quota
+pub struct ClientAuth {
+    clients: Vec<ClientToken>,
+    default_quota: u32,
+    admin_token: Option<String>,
+    windows: Mutex<HashMap<String, QuotaWindow>>,
+}
+
+struct QuotaWindow {
+    start: Instant,
+    count: u32,
+}
+
+impl ClientAuth {
+    pub fn new(clients: Vec<ClientToken>, default_quota: u32, admin_token: Option<String>) -> Self {
+        Self {
+            clients,
+            default_quota,
+            admin_token,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.clients.is_empty()
+    }
+
+    /// Checks an `Authorization: Bearer <token>` header value against the admin token, always fails without one
+    pub fn authenticate_admin(&self, authorization: Option<&str>) -> Result<(), ServerError> {
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or(ServerError::Unauthorized)?;
+
+        match &self.admin_token {
+            Some(admin_token) if constant_time_eq(admin_token.as_bytes(), token.as_bytes()) => Ok(()),
+            _ => Err(ServerError::Unauthorized),
+        }
+    }
+
+    /// Resolves the client from an `Authorization: Bearer <token>` header value
+    pub fn authenticate(&self, authorization: Option<&str>) -> Result<&ClientToken, ServerError> {
+        let token = authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or(ServerError::Unauthorized)?;
+
+        // Compare against every token so the response time does not reveal matching prefixes
+        self.clients
+            .iter()
+            .fold(None, |found, client| {
+                if constant_time_eq(client.token.as_bytes(), token.as_bytes()) {
+                    Some(client)
+                } else {
+                    found
+                }
+            })
+            .ok_or(ServerError::Unauthorized)
+    }
+
+    /// Counts `units` requests against the client's quota for the current minute, all or none of them
+    pub fn check_quota(&self, client: &ClientToken, units: u32) -> Result<(), ServerError> {
+        let limit = client.requests_per_minute.unwrap_or(self.default_quota);
+        let now = Instant::now();
+
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows
+            .entry(client.name.clone())
+            .or_insert(QuotaWindow { start: now, count: 0 });
+        if now.duration_since(window.start) >= QUOTA_WINDOW {
+            window.start = now;
+            window.count = 0;
+        }
+
+        if window.count.saturating_add(units) > limit {
+            let retry_after = QUOTA_WINDOW.saturating_sub(now.duration_since(window.start));
+            return Err(ServerError::QuotaExceeded(retry_after));
+        }
+        window.count += units;
+        Ok(())
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
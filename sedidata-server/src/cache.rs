@@ -1,11 +1,29 @@
 use chrono::{DateTime, Duration, Utc};
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::Path, sync::Arc};
-use tokio::sync::RwLock;
+use rusqlite::{params, types::Type, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tracing::{debug, error, info, warn};
 
-use crate::model::{ChampionMastery, LeagueEntry};
+use crate::{
+    config::{CacheTtls, Config},
+    metrics::Metrics,
+    model::{
+        normalize_riot_id, CacheMapStats, CacheStats, ChallengePoints, ChampionMastery, ChampionMasteryDetail,
+        LeagueEntry, LeagueSnapshot, MatchInfo, PlayerChallenge, RiotId,
+    },
+    region::Platform,
+};
 
-const CACHE_FILE: &str = "cache.json";
+/// Name of the JSON cache written by older versions, looked for next to the cache database
+const LEGACY_CACHE_FILE: &str = "cache.json";
+/// LP history is not part of the size limited cache, but very old snapshots are dropped
+const LEAGUE_HISTORY_RETENTION: Duration = Duration::days(365);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PuuidCacheEntry {
@@ -25,206 +43,1269 @@ pub struct ChampionMasteryCacheEntry {
     pub cached_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct CacheData {
-    #[serde(serialize_with = "serialize_tuple_map", deserialize_with = "deserialize_tuple_map")]
-    puuid_cache: HashMap<(String, String), PuuidCacheEntry>,
+/// Riot ID that account-v1 did not know
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissingAccountCacheEntry {
+    pub cached_at: DateTime<Utc>,
+}
 
-    player_data_cache: HashMap<String, PlayerDataCacheEntry>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiotIdCacheEntry {
+    pub riot_id: RiotId,
+    pub cached_at: DateTime<Utc>,
+}
 
-    #[serde(serialize_with = "serialize_tuple_map", deserialize_with = "deserialize_tuple_map")]
-    champion_mastery_cache: HashMap<(String, String), ChampionMasteryCacheEntry>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MasteryListCacheEntry {
+    pub score: u64,
+    pub masteries: Vec<ChampionMasteryDetail>,
+    pub cached_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct Pair {
-    first: String,
-    second: String,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerChallengesCacheEntry {
+    pub total_points: ChallengePoints,
+    pub category_points: BTreeMap<String, ChallengePoints>,
+    pub challenges: Vec<PlayerChallenge>,
+    pub cached_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct TupleEntry<V> {
-    key: Pair,
-    value: V,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallengePercentilesCacheEntry {
+    pub percentiles: BTreeMap<u64, BTreeMap<String, f64>>,
+    pub cached_at: DateTime<Utc>,
 }
 
-fn serialize_tuple_map<V, S>(map: &HashMap<(String, String), V>, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-    V: Serialize,
-{
-    let entries: Vec<TupleEntry<&V>> = map
-        .iter()
-        .map(|((first, second), value)| TupleEntry {
-            key: Pair {
-                first: first.clone(),
-                second: second.clone(),
-            },
-            value,
-        })
-        .collect();
+type PuuidKey = (Platform, String, String);
+type PlayerDataKey = (Platform, String);
+type ChampionMasteryKey = (Platform, String, String);
+type MatchKey = (Platform, String);
+type MasteryListKey = (Platform, String);
+type RiotIdKey = (Platform, String);
+type PlayerChallengesKey = (Platform, String);
+type ChallengePercentilesKey = (Platform,);
 
-    entries.serialize(serializer)
+/// The logical maps stored in the cache database, each with its own retention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheMap {
+    Puuid,
+    /// Riot IDs that do not exist, so typos are not looked up again on every refresh
+    MissingAccount,
+    PlayerData,
+    ChampionMastery,
+    Match,
+    MasteryList,
+    RiotId,
+    PlayerChallenges,
+    /// Shared by all players of a platform
+    ChallengePercentiles,
 }
 
-fn deserialize_tuple_map<'de, V, D>(deserializer: D) -> Result<HashMap<(String, String), V>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-    V: Deserialize<'de>,
-{
-    let entries: Vec<TupleEntry<V>> = Vec::deserialize(deserializer)?;
-    Ok(entries
-        .into_iter()
-        .map(|entry| ((entry.key.first, entry.key.second), entry.value))
-        .collect())
+impl CacheMap {
+    pub const ALL: [CacheMap; 9] = [
+        CacheMap::Puuid,
+        CacheMap::MissingAccount,
+        CacheMap::PlayerData,
+        CacheMap::ChampionMastery,
+        CacheMap::Match,
+        CacheMap::MasteryList,
+        CacheMap::RiotId,
+        CacheMap::PlayerChallenges,
+        CacheMap::ChallengePercentiles,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheMap::Puuid => "puuid",
+            CacheMap::MissingAccount => "missing_account",
+            CacheMap::PlayerData => "player_data",
+            CacheMap::ChampionMastery => "champion_mastery",
+            CacheMap::Match => "match",
+            CacheMap::MasteryList => "mastery_list",
+            CacheMap::RiotId => "riot_id",
+            CacheMap::PlayerChallenges => "player_challenges",
+            CacheMap::ChallengePercentiles => "challenge_percentiles",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<CacheMap> {
+        CacheMap::ALL.into_iter().find(|map| map.as_str() == name)
+    }
+
+    /// How long an entry is served as fresh, maps without TTL stay valid until their retention ends
+    fn ttl(&self, ttls: &CacheTtls) -> Option<std::time::Duration> {
+        match self {
+            CacheMap::Puuid => None,
+            CacheMap::MissingAccount => Some(ttls.negative),
+            CacheMap::PlayerData => Some(ttls.player_data),
+            CacheMap::ChampionMastery => Some(ttls.champion_mastery),
+            CacheMap::Match => None,
+            CacheMap::MasteryList => Some(ttls.mastery_list),
+            CacheMap::RiotId => Some(ttls.riot_id),
+            CacheMap::PlayerChallenges => Some(ttls.player_challenges),
+            CacheMap::ChallengePercentiles => Some(ttls.challenge_percentiles),
+        }
+    }
+
+    /// How long an entry is kept at all, independent of how long it is considered fresh.
+    /// Entries without retention are only removed by the size limit
+    fn retention(&self) -> Option<Duration> {
+        match self {
+            CacheMap::Puuid => Some(Duration::days(30)),
+            CacheMap::MissingAccount => Some(Duration::days(1)),
+            CacheMap::PlayerData => Some(Duration::days(7)),
+            CacheMap::ChampionMastery => Some(Duration::days(7)),
+            CacheMap::Match => None,
+            CacheMap::MasteryList => Some(Duration::days(7)),
+            CacheMap::RiotId => Some(Duration::days(30)),
+            CacheMap::PlayerChallenges => Some(Duration::days(7)),
+            CacheMap::ChallengePercentiles => Some(Duration::days(7)),
+        }
+    }
 }
 
-pub struct Cache {
-    puuid_cache: Arc<RwLock<HashMap<(String, String), PuuidCacheEntry>>>,
-    player_data_cache: Arc<RwLock<HashMap<String, PlayerDataCacheEntry>>>,
-    champion_mastery_cache: Arc<RwLock<HashMap<(String, String), ChampionMasteryCacheEntry>>>,
+/// Whole cache content as written by `cache export`, keys and values are kept as stored
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheExport {
+    pub entries: Vec<ExportedEntry>,
+    pub league_history: Vec<ExportedSnapshot>,
 }
 
-impl Clone for Cache {
-    fn clone(&self) -> Self {
-        Self {
-            puuid_cache: Arc::clone(&self.puuid_cache),
-            player_data_cache: Arc::clone(&self.player_data_cache),
-            champion_mastery_cache: Arc::clone(&self.champion_mastery_cache),
-        }
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedEntry {
+    pub map: String,
+    pub key: Box<RawValue>,
+    pub value: Box<RawValue>,
+    pub cached_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedSnapshot {
+    pub platform: String,
+    pub puuid: String,
+    pub queue_type: String,
+    pub tier: String,
+    pub rank: String,
+    pub league_points: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub recorded_at: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub entries: usize,
+    pub snapshots: usize,
+    /// Entries of cache maps this version does not know
+    pub skipped: usize,
+}
+
+/// Writes waiting for the next flush
+#[derive(Default)]
+struct PendingWrites {
+    /// Serialized entries by (map, key) with (value, cached_at)
+    entries: HashMap<(CacheMap, String), (String, i64)>,
+    /// League snapshots in the order they were recorded
+    snapshots: Vec<PendingSnapshot>,
+}
+
+#[derive(Clone)]
+struct PendingSnapshot {
+    platform: Platform,
+    puuid: String,
+    entry: LeagueEntry,
+    recorded_at: i64,
+}
+
+impl PendingSnapshot {
+    fn is_same_queue(&self, platform: Platform, puuid: &str, queue_type: &str) -> bool {
+        self.platform == platform && self.puuid == puuid && self.entry.queue_type == queue_type
     }
 }
 
+/// Writes are buffered in memory and written to the database in one transaction by `flush`,
+/// so request handlers never wait for the disk
+#[derive(Clone)]
+pub struct Cache {
+    conn: Arc<Mutex<Connection>>,
+    pending: Arc<Mutex<PendingWrites>>,
+    /// Held while a flush writes, so concurrent flushes do not write or clear the same entries twice
+    flushing: Arc<tokio::sync::Mutex<()>>,
+    max_entries: usize,
+    ttls: CacheTtls,
+    metrics: Arc<Metrics>,
+}
+
 impl Cache {
-    pub fn new() -> Self {
-        let (puuid_cache, player_data_cache, champion_mastery_cache) = Self::load_from_disk();
+    pub fn new(
+        path: &str,
+        max_entries: usize,
+        ttls: CacheTtls,
+        metrics: Arc<Metrics>,
+    ) -> Result<Self, rusqlite::Error> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS cache_entries (
+                 map TEXT NOT NULL,
+                 key TEXT NOT NULL,
+                 value TEXT NOT NULL,
+                 cached_at INTEGER NOT NULL,
+                 PRIMARY KEY (map, key)
+             );
+             CREATE INDEX IF NOT EXISTS idx_cache_entries_cached_at ON cache_entries (cached_at);
+             CREATE TABLE IF NOT EXISTS league_history (
+                 platform TEXT NOT NULL,
+                 puuid TEXT NOT NULL,
+                 queue_type TEXT NOT NULL,
+                 tier TEXT NOT NULL,
+                 rank TEXT NOT NULL,
+                 league_points INTEGER NOT NULL,
+                 wins INTEGER NOT NULL,
+                 losses INTEGER NOT NULL,
+                 recorded_at INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS idx_league_history_player
+                 ON league_history (platform, puuid, queue_type, recorded_at);",
+        )?;
 
-        Self {
-            puuid_cache: Arc::new(RwLock::new(puuid_cache)),
-            player_data_cache: Arc::new(RwLock::new(player_data_cache)),
-            champion_mastery_cache: Arc::new(RwLock::new(champion_mastery_cache)),
-        }
+        let cache = Self {
+            conn: Arc::new(Mutex::new(conn)),
+            pending: Arc::new(Mutex::new(PendingWrites::default())),
+            flushing: Arc::new(tokio::sync::Mutex::new(())),
+            max_entries,
+            ttls,
+            metrics,
+        };
+
+        info!("Opened cache database at {} ({} entries)", path, cache.count_entries());
+        Ok(cache)
     }
 
-    #[allow(clippy::type_complexity)]
-    fn load_from_disk() -> (
-        HashMap<(String, String), PuuidCacheEntry>,
-        HashMap<String, PlayerDataCacheEntry>,
-        HashMap<(String, String), ChampionMasteryCacheEntry>,
-    ) {
-        if !Path::new(CACHE_FILE).exists() {
-            println!("No cache file found, starting with empty cache");
-            return (HashMap::new(), HashMap::new(), HashMap::new());
-        }
-
-        match std::fs::read_to_string(CACHE_FILE) {
-            Ok(contents) => match serde_json::from_str::<CacheData>(&contents) {
-                Ok(data) => {
-                    println!(
-                        "Loaded cache: {} PUUIDs, {} player data entries, {} champion mastery entries",
-                        data.puuid_cache.len(),
-                        data.player_data_cache.len(),
-                        data.champion_mastery_cache.len()
-                    );
-                    (data.puuid_cache, data.player_data_cache, data.champion_mastery_cache)
-                }
-                Err(e) => {
-                    eprintln!("Failed to parse cache file: {}", e);
-                    (HashMap::new(), HashMap::new(), HashMap::new())
+    /// Opens the configured cache database outside of a running server
+    pub fn open(config: &Config) -> Result<Self, rusqlite::Error> {
+        Self::new(
+            &config.cache_path,
+            config.cache_max_entries,
+            config.ttls,
+            Arc::new(Metrics::new()),
+        )
+    }
+
+    /// Imports an existing `cache.json` next to the database at `path` once and renames it so it is
+    /// not imported again. An in-memory database has no directory to look in
+    pub fn migrate_legacy_file(&self, path: &str) {
+        if path == ":memory:" {
+            return;
+        }
+        let legacy_path = Path::new(path).with_file_name(LEGACY_CACHE_FILE);
+        if !legacy_path.exists() {
+            return;
+        }
+
+        let data = match std::fs::read_to_string(&legacy_path) {
+            Ok(contents) => match parse_legacy_cache(&contents) {
+                Some(data) => data,
+                None => {
+                    error!("Failed to parse legacy cache file, skipping migration");
+                    return;
                 }
             },
             Err(e) => {
-                eprintln!("Failed to read cache file: {}", e);
-                (HashMap::new(), HashMap::new(), HashMap::new())
+                error!("Failed to read legacy cache file: {}", e);
+                return;
             }
+        };
+
+        let mut conn = self.conn.lock().unwrap();
+        let result = (|| {
+            let tx = conn.transaction()?;
+            let now = Utc::now();
+            for ((platform, name, tagline), entry) in &data.puuid_cache {
+                let (name, tagline) = normalize_riot_id(name, tagline);
+                insert_entry(&tx, CacheMap::Puuid, &(platform, name, tagline), entry, now)?;
+            }
+            for (key, entry) in &data.player_data_cache {
+                insert_entry(&tx, CacheMap::PlayerData, key, entry, entry.cached_at)?;
+            }
+            for (key, entry) in &data.champion_mastery_cache {
+                insert_entry(&tx, CacheMap::ChampionMastery, key, entry, entry.cached_at)?;
+            }
+            tx.commit()
+        })();
+
+        match result {
+            Ok(()) => {
+                info!(
+                    "Migrated legacy cache: {} PUUIDs, {} player data entries, {} champion mastery entries",
+                    data.puuid_cache.len(),
+                    data.player_data_cache.len(),
+                    data.champion_mastery_cache.len()
+                );
+                if let Err(e) = std::fs::rename(&legacy_path, legacy_path.with_extension("json.migrated")) {
+                    warn!("Failed to rename legacy cache file: {}", e);
+                }
+            }
+            Err(e) => error!("Failed to migrate legacy cache file: {}", e),
         }
     }
 
-    async fn save_to_disk(&self) {
-        let puuid_cache = self.puuid_cache.read().await.clone();
-        let player_data_cache = self.player_data_cache.read().await.clone();
-        let champion_mastery_cache = self.champion_mastery_cache.read().await.clone();
+    fn count_entries(&self) -> u64 {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COUNT(*) FROM cache_entries", [], |row| row.get(0))
+            .unwrap_or(0)
+    }
 
-        let data = CacheData {
-            puuid_cache,
-            player_data_cache,
-            champion_mastery_cache,
+    /// Writes all buffered entries and snapshots in a single transaction. They stay readable from
+    /// the buffer until the transaction commits, and are kept for the next flush if it fails
+    pub async fn flush(&self) {
+        let _flushing = self.flushing.lock().await;
+        self.flush_locked().await;
+    }
+
+    /// `flush` for callers that already hold the flushing lock
+    async fn flush_locked(&self) {
+        let (entries, snapshots) = {
+            let pending = self.pending.lock().unwrap();
+            (pending.entries.clone(), pending.snapshots.clone())
         };
+        if entries.is_empty() && snapshots.is_empty() {
+            return;
+        }
+
+        let conn = Arc::clone(&self.conn);
+        let result = tokio::task::spawn_blocking(move || {
+            let result = (|| -> rusqlite::Result<usize> {
+                let mut conn = conn.lock().unwrap();
+                let tx = conn.transaction()?;
+                {
+                    let mut stmt = tx.prepare(
+                        "INSERT OR REPLACE INTO cache_entries (map, key, value, cached_at) VALUES (?1, ?2, ?3, ?4)",
+                    )?;
+                    for ((map, key), (value, cached_at)) in &entries {
+                        stmt.execute(params![map.as_str(), key, value, cached_at])?;
+                    }
+                }
+                let mut recorded = 0;
+                for snapshot in &snapshots {
+                    if insert_snapshot_if_changed(&tx, snapshot)? {
+                        recorded += 1;
+                    }
+                }
+                tx.commit()?;
+                Ok(recorded)
+            })();
+            (result, entries, snapshots.len())
+        })
+        .await;
 
-        match serde_json::to_string_pretty(&data) {
-            Ok(json) => {
-                if let Err(e) = std::fs::write(CACHE_FILE, json) {
-                    eprintln!("Failed to write cache to disk: {}", e);
-                } else {
-                    println!("  Cache saved to disk");
+        match result {
+            Ok((Ok(recorded), entries, snapshots)) => {
+                debug!(
+                    "Flushed {} cache entries and {} league snapshots",
+                    entries.len(),
+                    recorded
+                );
+                let mut pending = self.pending.lock().unwrap();
+                // Entries stored again since the flush started are newer, keep those
+                for (key, value) in entries {
+                    if pending.entries.get(&key) == Some(&value) {
+                        pending.entries.remove(&key);
+                    }
                 }
+                // Snapshots are only appended, the written ones are still at the front
+                pending.snapshots.drain(..snapshots);
+            }
+            Ok((Err(e), entries, snapshots)) => error!(
+                "Failed to flush {} cache entries and {} league snapshots: {}",
+                entries.len(),
+                snapshots,
+                e
+            ),
+            Err(e) => error!("Cache flush task failed: {}", e),
+        }
+    }
+
+    /// Refreshes the cache size gauges with the current number of entries per cache map
+    pub async fn update_size_metrics(&self) {
+        // Count buffered entries as well
+        self.flush().await;
+        let conn = Arc::clone(&self.conn);
+        let result = tokio::task::spawn_blocking(move || -> rusqlite::Result<HashMap<String, u64>> {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare("SELECT map, COUNT(*) FROM cache_entries GROUP BY map")?;
+            let counts = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(counts)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(counts)) => {
+                for map in CacheMap::ALL {
+                    let count = counts.get(map.as_str()).copied().unwrap_or(0);
+                    self.metrics.set_cache_entries(map, count);
+                }
+            }
+            Ok(Err(e)) => error!("Failed to count cache entries: {}", e),
+            Err(e) => error!("Cache count task failed: {}", e),
+        }
+    }
+
+    /// Drops entries past their retention and trims the oldest entries beyond the size limit
+    pub async fn evict(&self) {
+        self.flush().await;
+        let conn = Arc::clone(&self.conn);
+        let max_entries = self.max_entries;
+
+        let result = tokio::task::spawn_blocking(move || -> rusqlite::Result<usize> {
+            let conn = conn.lock().unwrap();
+            let mut removed = 0;
+            for map in CacheMap::ALL {
+                let Some(retention) = map.retention() else {
+                    continue;
+                };
+                let cutoff = (Utc::now() - retention).timestamp();
+                removed += conn.execute(
+                    "DELETE FROM cache_entries WHERE map = ?1 AND cached_at < ?2",
+                    params![map.as_str(), cutoff],
+                )?;
+            }
+
+            let history_cutoff = (Utc::now() - LEAGUE_HISTORY_RETENTION).timestamp();
+            removed += conn.execute(
+                "DELETE FROM league_history WHERE recorded_at < ?1",
+                params![history_cutoff],
+            )?;
+
+            let count: i64 = conn.query_row("SELECT COUNT(*) FROM cache_entries", [], |row| row.get(0))?;
+            let excess = count - max_entries as i64;
+            if excess > 0 {
+                removed += conn.execute(
+                    "DELETE FROM cache_entries WHERE rowid IN (
+                         SELECT rowid FROM cache_entries ORDER BY cached_at ASC LIMIT ?1
+                     )",
+                    params![excess],
+                )?;
+            }
+            Ok(removed)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(0)) => {}
+            Ok(Ok(removed)) => info!("Evicted {} cache entries", removed),
+            Ok(Err(e)) => error!("Failed to evict cache entries: {}", e),
+            Err(e) => error!("Cache eviction task failed: {}", e),
+        }
+    }
+
+    /// Like `is_fresh`, but negative (empty) results expire after the negative TTL if that is shorter
+    fn within_ttl(&self, cached_at: DateTime<Utc>, negative: bool, ttl: std::time::Duration) -> bool {
+        let ttl = if negative { ttl.min(self.ttls.negative) } else { ttl };
+        is_fresh(cached_at, ttl)
+    }
+
+    /// Counts a lookup as hit or miss, expired entries count as misses
+    fn record_lookup<V>(&self, map: CacheMap, entry: Option<V>) -> Option<V> {
+        self.metrics.record_cache_lookup(map, entry.is_some());
+        entry
+    }
+
+    async fn get_entry<K, V>(&self, map: CacheMap, key: &K) -> Option<V>
+    where
+        K: Serialize,
+        V: DeserializeOwned + Send + 'static,
+    {
+        let key = serde_json::to_string(key).ok()?;
+        let pending = self
+            .pending
+            .lock()
+            .unwrap()
+            .entries
+            .get(&(map, key.clone()))
+            .map(|(value, _)| value.clone());
+        if let Some(value) = pending {
+            return serde_json::from_str(&value).ok();
+        }
+
+        let conn = Arc::clone(&self.conn);
+        let result = tokio::task::spawn_blocking(move || -> rusqlite::Result<Option<String>> {
+            let conn = conn.lock().unwrap();
+            conn.query_row(
+                "SELECT value FROM cache_entries WHERE map = ?1 AND key = ?2",
+                params![map.as_str(), key],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(value)) => value.and_then(|v| serde_json::from_str(&v).ok()),
+            Ok(Err(e)) => {
+                error!("Failed to read {} cache entry: {}", map.as_str(), e);
+                None
             }
             Err(e) => {
-                eprintln!("Failed to serialize cache: {}", e);
+                error!("Cache read task failed: {}", e);
+                None
             }
         }
     }
 
-    pub async fn get_puuid(&self, name: &str, tagline: &str) -> Option<String> {
-        let cache = self.puuid_cache.read().await;
-        cache
-            .get(&(name.to_string(), tagline.to_string()))
-            .map(|entry| entry.puuid.clone())
+    async fn store_entry<K, V>(&self, map: CacheMap, key: &K, value: &V, cached_at: DateTime<Utc>)
+    where
+        K: Serialize,
+        V: Serialize,
+    {
+        let (Ok(key), Ok(value)) = (serde_json::to_string(key), serde_json::to_string(value)) else {
+            error!("Failed to serialize {} cache entry", map.as_str());
+            return;
+        };
+
+        self.pending
+            .lock()
+            .unwrap()
+            .entries
+            .insert((map, key), (value, cached_at.timestamp()));
     }
 
-    pub async fn store_puuid(&self, name: String, tagline: String, puuid: String) {
-        let mut cache = self.puuid_cache.write().await;
-        cache.insert((name, tagline), PuuidCacheEntry { puuid });
-        drop(cache);
-        self.save_to_disk().await;
+    async fn remove_entry<K: Serialize>(&self, map: CacheMap, key: &K) {
+        let Ok(key) = serde_json::to_string(key) else {
+            return;
+        };
+        // Otherwise a running flush could write the entry back after it was deleted
+        let _flushing = self.flushing.lock().await;
+        self.pending.lock().unwrap().entries.remove(&(map, key.clone()));
+
+        let conn = Arc::clone(&self.conn);
+        let result = tokio::task::spawn_blocking(move || {
+            let conn = conn.lock().unwrap();
+            conn.execute(
+                "DELETE FROM cache_entries WHERE map = ?1 AND key = ?2",
+                params![map.as_str(), key],
+            )
+        })
+        .await;
+
+        match result {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => error!("Failed to remove {} cache entry: {}", map.as_str(), e),
+            Err(e) => error!("Cache remove task failed: {}", e),
+        }
+    }
+
+    pub async fn get_puuid(&self, platform: Platform, name: &str, tagline: &str) -> Option<String> {
+        let entry = self.peek_puuid(platform, name, tagline).await;
+        self.record_lookup(CacheMap::Puuid, entry)
     }
 
-    pub async fn get_player_data(&self, puuid: &str) -> Option<PlayerDataCacheEntry> {
-        let cache = self.player_data_cache.read().await;
-        let entry = cache.get(puuid)?;
+    pub async fn store_puuid(&self, platform: Platform, name: &str, tagline: &str, puuid: String) {
+        let key = puuid_key(platform, name, tagline);
+        self.store_entry(CacheMap::Puuid, &key, &PuuidCacheEntry { puuid }, Utc::now())
+            .await;
+    }
 
-        // Check if cached in the last hour
-        let now = Utc::now();
-        let age = now.signed_duration_since(entry.cached_at);
+    /// Forgets which account a Riot ID belongs to, e.g. after the account was renamed
+    pub async fn remove_puuid(&self, platform: Platform, name: &str, tagline: &str) {
+        let key = puuid_key(platform, name, tagline);
+        self.remove_entry(CacheMap::Puuid, &key).await;
+    }
 
-        (age < Duration::hours(1)).then_some(entry.clone())
+    /// When account-v1 last reported the Riot ID as unknown, if that is recent enough to trust
+    pub async fn get_missing_account(&self, platform: Platform, name: &str, tagline: &str) -> Option<DateTime<Utc>> {
+        let cached_at = self.peek_missing_account(platform, name, tagline).await;
+        self.record_lookup(CacheMap::MissingAccount, cached_at)
     }
 
-    pub async fn store_player_data(&self, puuid: String, level: u64, ranked_stats: Vec<LeagueEntry>) {
-        let mut cache = self.player_data_cache.write().await;
-        cache.insert(
-            puuid,
-            PlayerDataCacheEntry {
-                level,
-                ranked_stats,
-                cached_at: Utc::now(),
-            },
-        );
-        drop(cache);
-        self.save_to_disk().await;
+    /// Like `get_missing_account`, but not counted as a lookup
+    pub async fn peek_missing_account(&self, platform: Platform, name: &str, tagline: &str) -> Option<DateTime<Utc>> {
+        let key = puuid_key(platform, name, tagline);
+        self.get_entry::<_, MissingAccountCacheEntry>(CacheMap::MissingAccount, &key)
+            .await
+            .filter(|entry| is_fresh(entry.cached_at, self.ttls.negative))
+            .map(|entry| entry.cached_at)
     }
 
-    pub async fn get_champion_mastery(&self, puuid: &str, champion: &str) -> Option<ChampionMasteryCacheEntry> {
-        let cache = self.champion_mastery_cache.read().await;
-        let entry = cache.get(&(puuid.to_string(), champion.to_string()))?;
+    pub async fn store_missing_account(&self, platform: Platform, name: &str, tagline: &str) {
+        let key = puuid_key(platform, name, tagline);
+        let cached_at = Utc::now();
+        self.store_entry(
+            CacheMap::MissingAccount,
+            &key,
+            &MissingAccountCacheEntry { cached_at },
+            cached_at,
+        )
+        .await;
+    }
 
-        // Check if cached in the last hour
-        let now = Utc::now();
-        let age = now.signed_duration_since(entry.cached_at);
+    /// Forgets that a Riot ID was unknown, e.g. because it was registered since
+    pub async fn remove_missing_account(&self, platform: Platform, name: &str, tagline: &str) {
+        let key = puuid_key(platform, name, tagline);
+        self.remove_entry(CacheMap::MissingAccount, &key).await;
+    }
 
-        (age < Duration::hours(1)).then_some(entry.clone())
+    pub async fn get_riot_id(&self, platform: Platform, puuid: &str) -> Option<RiotId> {
+        let key: RiotIdKey = (platform, puuid.to_string());
+        let entry = self
+            .get_entry::<_, RiotIdCacheEntry>(CacheMap::RiotId, &key)
+            .await
+            .filter(|entry| is_fresh(entry.cached_at, self.ttls.riot_id));
+        self.record_lookup(CacheMap::RiotId, entry).map(|entry| entry.riot_id)
     }
 
-    pub async fn store_champion_mastery(&self, puuid: String, champion: String, mastery: ChampionMastery) {
-        let mut cache = self.champion_mastery_cache.write().await;
-        cache.insert(
-            (puuid, champion),
-            ChampionMasteryCacheEntry {
-                mastery,
-                cached_at: Utc::now(),
-            },
-        );
-        drop(cache);
-        self.save_to_disk().await;
+    pub async fn store_riot_id(&self, platform: Platform, puuid: String, riot_id: RiotId) {
+        let key: RiotIdKey = (platform, puuid);
+        let cached_at = Utc::now();
+        self.store_entry(
+            CacheMap::RiotId,
+            &key,
+            &RiotIdCacheEntry { riot_id, cached_at },
+            cached_at,
+        )
+        .await;
+    }
+
+    pub async fn get_player_data(&self, platform: Platform, puuid: &str) -> Option<PlayerDataCacheEntry> {
+        let entry = self.peek_player_data(platform, puuid).await;
+        self.record_lookup(CacheMap::PlayerData, entry)
+    }
+
+    /// Like `get_player_data`, but not counted as a lookup
+    pub async fn peek_player_data(&self, platform: Platform, puuid: &str) -> Option<PlayerDataCacheEntry> {
+        let key: PlayerDataKey = (platform, puuid.to_string());
+        self.get_entry::<_, PlayerDataCacheEntry>(CacheMap::PlayerData, &key)
+            .await
+            .filter(|entry| self.within_ttl(entry.cached_at, entry.ranked_stats.is_empty(), self.ttls.player_data))
+    }
+
+    /// Player data regardless of its age, used when fresh data cannot be fetched
+    pub async fn get_expired_player_data(&self, platform: Platform, puuid: &str) -> Option<PlayerDataCacheEntry> {
+        let key: PlayerDataKey = (platform, puuid.to_string());
+        self.get_entry(CacheMap::PlayerData, &key).await
+    }
+
+    pub async fn store_player_data(&self, platform: Platform, puuid: String, entry: PlayerDataCacheEntry) {
+        let key: PlayerDataKey = (platform, puuid);
+        self.store_entry(CacheMap::PlayerData, &key, &entry, entry.cached_at)
+            .await;
+    }
+
+    pub async fn get_champion_mastery(
+        &self,
+        platform: Platform,
+        puuid: &str,
+        champion: &str,
+    ) -> Option<ChampionMasteryCacheEntry> {
+        let key: ChampionMasteryKey = (platform, puuid.to_string(), champion.to_string());
+        let entry = self
+            .get_entry::<_, ChampionMasteryCacheEntry>(CacheMap::ChampionMastery, &key)
+            .await
+            .filter(|entry| self.within_ttl(entry.cached_at, entry.mastery.is_empty(), self.ttls.champion_mastery));
+        self.record_lookup(CacheMap::ChampionMastery, entry)
+    }
+
+    /// Champion mastery regardless of its age, used when fresh data cannot be fetched
+    pub async fn get_expired_champion_mastery(
+        &self,
+        platform: Platform,
+        puuid: &str,
+        champion: &str,
+    ) -> Option<ChampionMasteryCacheEntry> {
+        let key: ChampionMasteryKey = (platform, puuid.to_string(), champion.to_string());
+        self.get_entry(CacheMap::ChampionMastery, &key).await
+    }
+
+    pub async fn store_champion_mastery(
+        &self,
+        platform: Platform,
+        puuid: String,
+        champion: String,
+        mastery: ChampionMastery,
+    ) {
+        let key: ChampionMasteryKey = (platform, puuid, champion);
+        let entry = ChampionMasteryCacheEntry {
+            mastery,
+            cached_at: Utc::now(),
+        };
+        self.store_entry(CacheMap::ChampionMastery, &key, &entry, entry.cached_at)
+            .await;
+    }
+
+    pub async fn get_mastery_list(&self, platform: Platform, puuid: &str) -> Option<MasteryListCacheEntry> {
+        let key: MasteryListKey = (platform, puuid.to_string());
+        let entry = self
+            .get_entry::<_, MasteryListCacheEntry>(CacheMap::MasteryList, &key)
+            .await
+            .filter(|entry| self.within_ttl(entry.cached_at, entry.masteries.is_empty(), self.ttls.mastery_list));
+        self.record_lookup(CacheMap::MasteryList, entry)
+    }
+
+    pub async fn store_mastery_list(
+        &self,
+        platform: Platform,
+        puuid: String,
+        score: u64,
+        masteries: Vec<ChampionMasteryDetail>,
+    ) {
+        let key: MasteryListKey = (platform, puuid);
+        let entry = MasteryListCacheEntry {
+            score,
+            masteries,
+            cached_at: Utc::now(),
+        };
+        self.store_entry(CacheMap::MasteryList, &key, &entry, entry.cached_at)
+            .await;
     }
+
+    pub async fn get_player_challenges(&self, platform: Platform, puuid: &str) -> Option<PlayerChallengesCacheEntry> {
+        let key: PlayerChallengesKey = (platform, puuid.to_string());
+        let entry = self
+            .get_entry::<_, PlayerChallengesCacheEntry>(CacheMap::PlayerChallenges, &key)
+            .await
+            .filter(|entry| {
+                self.within_ttl(
+                    entry.cached_at,
+                    entry.challenges.is_empty(),
+                    self.ttls.player_challenges,
+                )
+            });
+        self.record_lookup(CacheMap::PlayerChallenges, entry)
+    }
+
+    /// Player challenges regardless of their age, used when fresh data cannot be fetched
+    pub async fn get_expired_player_challenges(
+        &self,
+        platform: Platform,
+        puuid: &str,
+    ) -> Option<PlayerChallengesCacheEntry> {
+        let key: PlayerChallengesKey = (platform, puuid.to_string());
+        self.get_entry(CacheMap::PlayerChallenges, &key).await
+    }
+
+    pub async fn store_player_challenges(&self, platform: Platform, puuid: String, entry: PlayerChallengesCacheEntry) {
+        let key: PlayerChallengesKey = (platform, puuid);
+        self.store_entry(CacheMap::PlayerChallenges, &key, &entry, entry.cached_at)
+            .await;
+    }
+
+    pub async fn get_challenge_percentiles(&self, platform: Platform) -> Option<ChallengePercentilesCacheEntry> {
+        let entry = self.peek_challenge_percentiles(platform).await;
+        self.record_lookup(CacheMap::ChallengePercentiles, entry)
+    }
+
+    /// Like `get_challenge_percentiles`, but not counted as a lookup
+    pub async fn peek_challenge_percentiles(&self, platform: Platform) -> Option<ChallengePercentilesCacheEntry> {
+        let key: ChallengePercentilesKey = (platform,);
+        self.get_entry::<_, ChallengePercentilesCacheEntry>(CacheMap::ChallengePercentiles, &key)
+            .await
+            .filter(|entry| is_fresh(entry.cached_at, self.ttls.challenge_percentiles))
+    }
+
+    /// Percentiles regardless of their age, used when fresh data cannot be fetched
+    pub async fn get_expired_challenge_percentiles(
+        &self,
+        platform: Platform,
+    ) -> Option<ChallengePercentilesCacheEntry> {
+        let key: ChallengePercentilesKey = (platform,);
+        self.get_entry(CacheMap::ChallengePercentiles, &key).await
+    }
+
+    pub async fn store_challenge_percentiles(&self, platform: Platform, entry: ChallengePercentilesCacheEntry) {
+        let key: ChallengePercentilesKey = (platform,);
+        self.store_entry(CacheMap::ChallengePercentiles, &key, &entry, entry.cached_at)
+            .await;
+    }
+
+    pub async fn get_match(&self, platform: Platform, match_id: &str) -> Option<MatchInfo> {
+        let key: MatchKey = (platform, match_id.to_string());
+        let entry = self.get_entry(CacheMap::Match, &key).await;
+        self.record_lookup(CacheMap::Match, entry)
+    }
+
+    pub async fn store_match(&self, platform: Platform, match_id: String, info: MatchInfo) {
+        let key: MatchKey = (platform, match_id);
+        self.store_entry(CacheMap::Match, &key, &info, Utc::now()).await;
+    }
+
+    /// Buffers a snapshot for every queue, the next flush only keeps those whose tier, rank, LP,
+    /// wins or losses changed since the last one
+    pub async fn record_league_snapshots(&self, platform: Platform, puuid: String, entries: Vec<LeagueEntry>) {
+        let recorded_at = Utc::now().timestamp();
+        let mut pending = self.pending.lock().unwrap();
+        for entry in entries {
+            // Repeated lookups before a flush would otherwise pile up identical snapshots
+            let last = pending
+                .snapshots
+                .iter()
+                .rev()
+                .find(|snapshot| snapshot.is_same_queue(platform, &puuid, &entry.queue_type));
+            if last.is_some_and(|last| same_standing(&last.entry, &entry)) {
+                continue;
+            }
+            pending.snapshots.push(PendingSnapshot {
+                platform,
+                puuid: puuid.clone(),
+                entry,
+                recorded_at,
+            });
+        }
+    }
+
+    /// Snapshots of a player as (queue type, snapshot), oldest first within each queue
+    pub async fn get_league_history(
+        &self,
+        platform: Platform,
+        puuid: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> Vec<(String, LeagueSnapshot)> {
+        // Snapshots are only deduplicated against the database when they are written
+        self.flush().await;
+        let conn = Arc::clone(&self.conn);
+        let puuid = puuid.to_string();
+        let since = since.map_or(0, |since| since.timestamp());
+
+        let result = tokio::task::spawn_blocking(move || -> rusqlite::Result<Vec<(String, LeagueSnapshot)>> {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT queue_type, tier, rank, league_points, wins, losses, recorded_at FROM league_history
+                 WHERE platform = ?1 AND puuid = ?2 AND recorded_at >= ?3
+                 ORDER BY queue_type, recorded_at, rowid",
+            )?;
+            let rows = stmt.query_map(params![platform.as_str(), puuid, since], |row| {
+                let snapshot = LeagueSnapshot {
+                    tier: row.get(1)?,
+                    rank: row.get(2)?,
+                    league_points: row.get(3)?,
+                    wins: row.get(4)?,
+                    losses: row.get(5)?,
+                    recorded_at: DateTime::from_timestamp(row.get(6)?, 0).unwrap_or_default(),
+                };
+                Ok((row.get(0)?, snapshot))
+            })?;
+            rows.collect()
+        })
+        .await;
+
+        match result {
+            Ok(Ok(history)) => history,
+            Ok(Err(e)) => {
+                error!("Failed to read league history: {}", e);
+                Vec::new()
+            }
+            Err(e) => {
+                error!("League history task failed: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    // =======================================================================
+    //   Administration, errors are returned to the caller instead of logged
+    // =======================================================================
+
+    /// Runs a database operation on the blocking thread pool
+    async fn with_conn<T, F>(&self, f: F) -> rusqlite::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = Arc::clone(&self.conn);
+        match tokio::task::spawn_blocking(move || f(&mut conn.lock().unwrap())).await {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    /// Entry count and age range per cache map, including entries not flushed yet
+    pub async fn stats(&self) -> rusqlite::Result<CacheStats> {
+        self.flush().await;
+        let ttls = self.ttls;
+        self.with_conn(move |conn| {
+            let now = Utc::now().timestamp();
+            let mut maps = BTreeMap::new();
+            for map in CacheMap::ALL {
+                // Without a TTL nothing expires, so the cutoff lies before every entry
+                let cutoff = map.ttl(&ttls).map_or(i64::MIN, |ttl| now - ttl.as_secs() as i64);
+                let stats = conn.query_row(
+                    "SELECT COUNT(*), COALESCE(SUM(cached_at <= ?2), 0), MIN(cached_at), MAX(cached_at)
+                     FROM cache_entries WHERE map = ?1",
+                    params![map.as_str(), cutoff],
+                    |row| {
+                        Ok(CacheMapStats {
+                            entries: row.get(0)?,
+                            expired: row.get(1)?,
+                            oldest: row
+                                .get::<_, Option<i64>>(2)?
+                                .and_then(|ts| DateTime::from_timestamp(ts, 0)),
+                            newest: row
+                                .get::<_, Option<i64>>(3)?
+                                .and_then(|ts| DateTime::from_timestamp(ts, 0)),
+                        })
+                    },
+                )?;
+                maps.insert(map.as_str().to_string(), stats);
+            }
+
+            let league_history_snapshots =
+                conn.query_row("SELECT COUNT(*) FROM league_history", [], |row| row.get(0))?;
+            Ok(CacheStats {
+                maps,
+                league_history_snapshots,
+            })
+        })
+        .await
+    }
+
+    /// Verifies that the database can be read
+    pub async fn check(&self) -> rusqlite::Result<()> {
+        self.with_conn(|conn| conn.query_row("SELECT COUNT(*) FROM cache_entries", [], |_| Ok(())))
+            .await
+    }
+
+    /// PUUID cached for a Riot ID, without counting as a lookup
+    pub async fn peek_puuid(&self, platform: Platform, name: &str, tagline: &str) -> Option<String> {
+        let key = puuid_key(platform, name, tagline);
+        self.get_entry::<_, PuuidCacheEntry>(CacheMap::Puuid, &key)
+            .await
+            .map(|entry| entry.puuid)
+    }
+
+    /// Removes every cached entry of a player, the LP history is kept
+    pub async fn remove_player(&self, platform: Platform, puuid: &str) -> rusqlite::Result<usize> {
+        // Like `remove_entry`, no flush may write the player back between our flush and delete
+        let _flushing = self.flushing.lock().await;
+        self.flush_locked().await;
+        let player_key = to_json(&(platform, puuid))?;
+        let puuid_value = to_json(&PuuidCacheEntry {
+            puuid: puuid.to_string(),
+        })?;
+        // Champion mastery keys are the player key with the champion appended
+        let mastery_prefix = format!("{},", player_key.trim_end_matches(']'));
+
+        // Lookups may have stored entries of the player since the flush
+        self.pending
+            .lock()
+            .unwrap()
+            .entries
+            .retain(|(map, key), (value, _)| match map {
+                CacheMap::Puuid => *value != puuid_value,
+                CacheMap::PlayerData | CacheMap::MasteryList | CacheMap::RiotId | CacheMap::PlayerChallenges => {
+                    *key != player_key
+                }
+                CacheMap::ChampionMastery => !key.starts_with(&mastery_prefix),
+                _ => true,
+            });
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let mut removed = tx.execute(
+                "DELETE FROM cache_entries WHERE map = ?1 AND value = ?2",
+                params![CacheMap::Puuid.as_str(), puuid_value],
+            )?;
+            for map in [
+                CacheMap::PlayerData,
+                CacheMap::MasteryList,
+                CacheMap::RiotId,
+                CacheMap::PlayerChallenges,
+            ] {
+                removed += tx.execute(
+                    "DELETE FROM cache_entries WHERE map = ?1 AND key = ?2",
+                    params![map.as_str(), player_key],
+                )?;
+            }
+            removed += tx.execute(
+                "DELETE FROM cache_entries WHERE map = ?1 AND substr(key, 1, length(?2)) = ?2",
+                params![CacheMap::ChampionMastery.as_str(), mastery_prefix],
+            )?;
+            tx.commit()?;
+            Ok(removed)
+        })
+        .await
+    }
+
+    /// Removes entries past their TTL, by cache map. They would otherwise still be served if the Riot API fails
+    pub async fn purge_expired(&self) -> rusqlite::Result<BTreeMap<String, usize>> {
+        self.flush().await;
+        let ttls = self.ttls;
+        self.with_conn(move |conn| {
+            let now = Utc::now().timestamp();
+            let mut removed = BTreeMap::new();
+            for map in CacheMap::ALL {
+                let Some(ttl) = map.ttl(&ttls) else {
+                    continue;
+                };
+                let count = conn.execute(
+                    "DELETE FROM cache_entries WHERE map = ?1 AND cached_at <= ?2",
+                    params![map.as_str(), now - ttl.as_secs() as i64],
+                )?;
+                removed.insert(map.as_str().to_string(), count);
+            }
+            Ok(removed)
+        })
+        .await
+    }
+
+    pub async fn export(&self) -> rusqlite::Result<CacheExport> {
+        self.flush().await;
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare("SELECT map, key, value, cached_at FROM cache_entries ORDER BY map, key")?;
+            let entries = stmt
+                .query_map([], |row| {
+                    Ok(ExportedEntry {
+                        map: row.get(0)?,
+                        key: raw_json(1, row.get(1)?)?,
+                        value: raw_json(2, row.get(2)?)?,
+                        cached_at: row.get(3)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            let mut stmt = conn.prepare(
+                "SELECT platform, puuid, queue_type, tier, rank, league_points, wins, losses, recorded_at
+                 FROM league_history ORDER BY rowid",
+            )?;
+            let league_history = stmt
+                .query_map([], |row| {
+                    Ok(ExportedSnapshot {
+                        platform: row.get(0)?,
+                        puuid: row.get(1)?,
+                        queue_type: row.get(2)?,
+                        tier: row.get(3)?,
+                        rank: row.get(4)?,
+                        league_points: row.get(5)?,
+                        wins: row.get(6)?,
+                        losses: row.get(7)?,
+                        recorded_at: row.get(8)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+
+            Ok(CacheExport {
+                entries,
+                league_history,
+            })
+        })
+        .await
+    }
+
+    /// Adds exported entries, existing entries are only replaced by newer ones and known snapshots are skipped
+    pub async fn import(&self, data: CacheExport) -> rusqlite::Result<ImportSummary> {
+        self.flush().await;
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let mut summary = ImportSummary::default();
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT INTO cache_entries (map, key, value, cached_at) VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT (map, key) DO UPDATE SET value = excluded.value, cached_at = excluded.cached_at
+                     WHERE excluded.cached_at > cache_entries.cached_at",
+                )?;
+                for entry in &data.entries {
+                    if CacheMap::parse(&entry.map).is_none() {
+                        summary.skipped += 1;
+                        continue;
+                    }
+                    summary.entries +=
+                        stmt.execute(params![entry.map, entry.key.get(), entry.value.get(), entry.cached_at])?;
+                }
+
+                let mut stmt = tx.prepare(
+                    "INSERT INTO league_history
+                         (platform, puuid, queue_type, tier, rank, league_points, wins, losses, recorded_at)
+                     SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9
+                     WHERE NOT EXISTS (
+                         SELECT 1 FROM league_history
+                         WHERE platform = ?1 AND puuid = ?2 AND queue_type = ?3 AND recorded_at = ?9
+                     )",
+                )?;
+                for snapshot in &data.league_history {
+                    summary.snapshots += stmt.execute(params![
+                        snapshot.platform,
+                        snapshot.puuid,
+                        snapshot.queue_type,
+                        snapshot.tier,
+                        snapshot.rank,
+                        snapshot.league_points,
+                        snapshot.wins,
+                        snapshot.losses,
+                        snapshot.recorded_at
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(summary)
+        })
+        .await
+    }
+}
+
+/// Appends a snapshot unless it matches the last one recorded for the same queue
+fn insert_snapshot_if_changed(tx: &rusqlite::Transaction, snapshot: &PendingSnapshot) -> rusqlite::Result<bool> {
+    let PendingSnapshot {
+        platform,
+        puuid,
+        entry,
+        recorded_at,
+    } = snapshot;
+    let last: Option<(String, String, i32, i32, i32)> = tx
+        .query_row(
+            "SELECT tier, rank, league_points, wins, losses FROM league_history
+             WHERE platform = ?1 AND puuid = ?2 AND queue_type = ?3
+             ORDER BY recorded_at DESC, rowid DESC LIMIT 1",
+            params![platform.as_str(), puuid, entry.queue_type],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+        )
+        .optional()?;
+
+    let current = (
+        entry.tier.clone(),
+        entry.rank.clone(),
+        entry.league_points,
+        entry.wins,
+        entry.losses,
+    );
+    if last.as_ref() == Some(&current) {
+        return Ok(false);
+    }
+
+    tx.execute(
+        "INSERT INTO league_history
+             (platform, puuid, queue_type, tier, rank, league_points, wins, losses, recorded_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            platform.as_str(),
+            puuid,
+            entry.queue_type,
+            entry.tier,
+            entry.rank,
+            entry.league_points,
+            entry.wins,
+            entry.losses,
+            recorded_at
+        ],
+    )?;
+    Ok(true)
+}
+
+fn same_standing(a: &LeagueEntry, b: &LeagueEntry) -> bool {
+    (&a.tier, &a.rank, a.league_points, a.wins, a.losses) == (&b.tier, &b.rank, b.league_points, b.wins, b.losses)
+}
+
+fn puuid_key(platform: Platform, name: &str, tagline: &str) -> PuuidKey {
+    let (name, tagline) = normalize_riot_id(name, tagline);
+    (platform, name, tagline)
+}
+
+/// Checks if an entry was cached within the given TTL
+fn is_fresh(cached_at: DateTime<Utc>, ttl: std::time::Duration) -> bool {
+    let age = Utc::now().signed_duration_since(cached_at);
+    Duration::from_std(ttl).is_ok_and(|ttl| age < ttl)
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+/// Stored keys and values are JSON, anything else means the database was modified by hand
+fn raw_json(column: usize, value: String) -> rusqlite::Result<Box<RawValue>> {
+    RawValue::from_string(value).map_err(|e| rusqlite::Error::FromSqlConversionFailure(column, Type::Text, Box::new(e)))
+}
+
+fn insert_entry<K, V>(
+    conn: &Connection,
+    map: CacheMap,
+    key: &K,
+    value: &V,
+    cached_at: DateTime<Utc>,
+) -> rusqlite::Result<()>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    let key = serde_json::to_string(key).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    let value = serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    conn.execute(
+        "INSERT OR IGNORE INTO cache_entries (map, key, value, cached_at) VALUES (?1, ?2, ?3, ?4)",
+        params![map.as_str(), key, value, cached_at.timestamp()],
+    )?;
+    Ok(())
+}
+
+// ===========================================================================
+//   Legacy cache.json format
+// ===========================================================================
+
+#[derive(Debug, Deserialize)]
+struct LegacyCacheData {
+    #[serde(deserialize_with = "deserialize_keyed_map")]
+    puuid_cache: HashMap<PuuidKey, PuuidCacheEntry>,
+
+    #[serde(deserialize_with = "deserialize_keyed_map")]
+    player_data_cache: HashMap<PlayerDataKey, PlayerDataCacheEntry>,
+
+    #[serde(deserialize_with = "deserialize_keyed_map")]
+    champion_mastery_cache: HashMap<ChampionMasteryKey, ChampionMasteryCacheEntry>,
+}
+
+/// Format written before platforms were part of the keys, all entries belong to EUW
+#[derive(Debug, Deserialize)]
+struct UnroutedCacheData {
+    #[serde(deserialize_with = "deserialize_keyed_map")]
+    puuid_cache: HashMap<Pair, PuuidCacheEntry>,
+
+    player_data_cache: HashMap<String, PlayerDataCacheEntry>,
+
+    #[serde(deserialize_with = "deserialize_keyed_map")]
+    champion_mastery_cache: HashMap<Pair, ChampionMasteryCacheEntry>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize)]
+struct Pair {
+    first: String,
+    second: String,
+}
+
+#[derive(Deserialize)]
+struct KeyedEntry<K, V> {
+    key: K,
+    value: V,
+}
+
+fn deserialize_keyed_map<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+{
+    let entries: Vec<KeyedEntry<K, V>> = Vec::deserialize(deserializer)?;
+    Ok(entries.into_iter().map(|entry| (entry.key, entry.value)).collect())
+}
+
+fn parse_legacy_cache(contents: &str) -> Option<LegacyCacheData> {
+    if let Ok(data) = serde_json::from_str::<LegacyCacheData>(contents) {
+        return Some(data);
+    }
+
+    let data = serde_json::from_str::<UnroutedCacheData>(contents).ok()?;
+    let platform = Platform::Euw1;
+    Some(LegacyCacheData {
+        puuid_cache: data
+            .puuid_cache
+            .into_iter()
+            .map(|(key, entry)| ((platform, key.first, key.second), entry))
+            .collect(),
+        player_data_cache: data
+            .player_data_cache
+            .into_iter()
+            .map(|(puuid, entry)| ((platform, puuid), entry))
+            .collect(),
+        champion_mastery_cache: data
+            .champion_mastery_cache
+            .into_iter()
+            .map(|(key, entry)| ((platform, key.first, key.second), entry))
+            .collect(),
+    })
 }
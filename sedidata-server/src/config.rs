@@ -0,0 +1,356 @@
+use std::{
+    collections::HashMap,
+    env, fmt,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use tracing_subscriber::EnvFilter;
+
+use crate::{region::Platform, riot_api_client::ROUTING_PLACEHOLDER};
+
+const CONFIG_FILE_ENV: &str = "SEDIDATA_CONFIG";
+const DEFAULT_CONFIG_FILE: &str = "sedidata.toml";
+
+/// Every supported option as (key in the config file, environment variable)
+const KEYS: [(&str, &str); 25] = [
+    ("bind_address", "SEDIDATA_BIND_ADDRESS"),
+    ("port", "PORT"),
+    ("cache_path", "SEDIDATA_CACHE_PATH"),
+    ("cache_max_entries", "SEDIDATA_CACHE_MAX_ENTRIES"),
+    ("cache_flush_interval_secs", "SEDIDATA_CACHE_FLUSH_INTERVAL_SECS"),
+    ("default_platform", "SEDIDATA_DEFAULT_PLATFORM"),
+    ("player_data_ttl_secs", "SEDIDATA_PLAYER_DATA_TTL_SECS"),
+    ("champion_mastery_ttl_secs", "SEDIDATA_CHAMPION_MASTERY_TTL_SECS"),
+    ("mastery_list_ttl_secs", "SEDIDATA_MASTERY_LIST_TTL_SECS"),
+    ("riot_id_ttl_secs", "SEDIDATA_RIOT_ID_TTL_SECS"),
+    ("negative_ttl_secs", "SEDIDATA_NEGATIVE_TTL_SECS"),
+    ("player_challenges_ttl_secs", "SEDIDATA_PLAYER_CHALLENGES_TTL_SECS"),
+    (
+        "challenge_percentiles_ttl_secs",
+        "SEDIDATA_CHALLENGE_PERCENTILES_TTL_SECS",
+    ),
+    ("stale_while_revalidate", "SEDIDATA_STALE_WHILE_REVALIDATE"),
+    ("key_check_interval_secs", "SEDIDATA_KEY_CHECK_INTERVAL_SECS"),
+    ("max_retries", "SEDIDATA_MAX_RETRIES"),
+    ("max_queue_wait_secs", "SEDIDATA_MAX_QUEUE_WAIT_SECS"),
+    ("default_retry_after_secs", "SEDIDATA_DEFAULT_RETRY_AFTER_SECS"),
+    ("log_level", "SEDIDATA_LOG_LEVEL"),
+    ("log_format", "SEDIDATA_LOG_FORMAT"),
+    ("riot_api_base_url", "SEDIDATA_RIOT_API_BASE_URL"),
+    ("riot_api_keys_file", "SEDIDATA_RIOT_API_KEYS_FILE"),
+    ("client_tokens", "SEDIDATA_CLIENT_TOKENS"),
+    ("client_quota_per_minute", "SEDIDATA_CLIENT_QUOTA_PER_MINUTE"),
+    ("admin_token", "SEDIDATA_ADMIN_TOKEN"),
+];
+
+/// Keys whose values are not repeated in error messages
+const SECRET_KEYS: [&str; 2] = ["client_tokens", "admin_token"];
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub cache_path: String,
+    pub cache_max_entries: usize,
+    /// How often buffered cache writes are written to the database
+    pub cache_flush_interval: Duration,
+    pub default_platform: Platform,
+    pub ttls: CacheTtls,
+    /// Answer with expired player data right away and refresh it in the background
+    pub stale_while_revalidate: bool,
+    /// How often the Riot API key is verified for `/ready`
+    pub key_check_interval: Duration,
+    pub retry: RetryPolicy,
+    /// Level or `tracing` filter directives, e.g. `debug` or `info,sedidata_server=debug`
+    pub log_level: String,
+    pub log_format: LogFormat,
+    /// Riot API location, `{routing}` is replaced by the platform or region (e.g. `euw1`, `europe`)
+    pub riot_api_base_url: String,
+    /// File with one Riot API key per line, replaces `RIOT_API_KEY` and is reloaded when it changes
+    pub riot_api_keys_file: Option<PathBuf>,
+    /// Clients allowed to use the API, anyone may use it if empty
+    pub client_tokens: Vec<ClientToken>,
+    /// Requests per minute for clients without their own quota
+    pub client_quota_per_minute: u32,
+    /// Token for the `/admin` routes, which are disabled without it
+    pub admin_token: Option<String>,
+}
+
+/// How long cached data is served before it is fetched again
+#[derive(Debug, Clone, Copy)]
+pub struct CacheTtls {
+    pub player_data: Duration,
+    pub champion_mastery: Duration,
+    pub mastery_list: Duration,
+    /// How long a PUUID is assumed to keep its Riot ID before checking for a rename
+    pub riot_id: Duration,
+    /// Upper bound for results without data: unknown Riot IDs, unranked players and unplayed champions
+    pub negative: Duration,
+    pub player_challenges: Duration,
+    /// Percentiles are global and only move slowly
+    pub challenge_percentiles: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// How often a request answered with 429 is retried
+    pub max_retries: usize,
+    /// Longest time a request may wait for rate limit budget before failing fast
+    pub max_queue_wait: Duration,
+    /// Fallback if Riot answers 429 without a `Retry-After` header
+    pub default_retry_after: Duration,
+}
+
+/// API token of one client, configured as `name:token` or `name:token:requests_per_minute`
+#[derive(Clone)]
+pub struct ClientToken {
+    pub name: String,
+    pub token: String,
+    pub requests_per_minute: Option<u32>,
+}
+
+impl fmt::Debug for ClientToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientToken")
+            .field("name", &self.name)
+            .field("token", &"***")
+            .field("requests_per_minute", &self.requests_per_minute)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::from([0, 0, 0, 0]),
+            port: 3000,
+            cache_path: "cache.db".to_string(),
+            cache_max_entries: 100_000,
+            cache_flush_interval: Duration::from_secs(5),
+            default_platform: Platform::Euw1,
+            ttls: CacheTtls {
+                player_data: Duration::from_secs(60 * 60),
+                champion_mastery: Duration::from_secs(60 * 60),
+                mastery_list: Duration::from_secs(60 * 60),
+                riot_id: Duration::from_secs(24 * 60 * 60),
+                negative: Duration::from_secs(10 * 60),
+                player_challenges: Duration::from_secs(60 * 60),
+                challenge_percentiles: Duration::from_secs(24 * 60 * 60),
+            },
+            stale_while_revalidate: false,
+            key_check_interval: Duration::from_secs(5 * 60),
+            retry: RetryPolicy {
+                max_retries: 3,
+                max_queue_wait: Duration::from_secs(5),
+                default_retry_after: Duration::from_secs(1),
+            },
+            log_level: "info".to_string(),
+            log_format: LogFormat::Text,
+            riot_api_base_url: "https://{routing}.api.riotgames.com".to_string(),
+            riot_api_keys_file: None,
+            client_tokens: Vec::new(),
+            client_quota_per_minute: 60,
+            admin_token: None,
+        }
+    }
+}
+
+impl Config {
+    /// Loads defaults, then the optional TOML file, then environment variables (highest priority)
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut errors = Vec::new();
+        let mut values = HashMap::new();
+
+        // Config file, explicitly given files must exist
+        let explicit_path = env::var(CONFIG_FILE_ENV).ok();
+        let path = explicit_path.clone().unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string());
+        if explicit_path.is_some() || Path::new(&path).exists() {
+            match read_config_file(&path) {
+                Ok(table) => {
+                    for (key, value) in table {
+                        if !KEYS.iter().any(|(k, _)| *k == key) {
+                            errors.push(format!("{}: unknown key in {}", key, path));
+                            continue;
+                        }
+                        match value {
+                            toml::Value::String(s) => values.insert(key, s),
+                            toml::Value::Integer(i) => values.insert(key, i.to_string()),
+                            toml::Value::Boolean(b) => values.insert(key, b.to_string()),
+                            other => {
+                                errors.push(format!(
+                                    "{}: expected string, integer or boolean, got {}",
+                                    key,
+                                    other.type_str()
+                                ));
+                                continue;
+                            }
+                        };
+                    }
+                }
+                Err(e) => errors.push(format!("{}: {}", path, e)),
+            }
+        }
+
+        // Environment overrides
+        for (key, env_name) in KEYS {
+            if let Ok(value) = env::var(env_name) {
+                values.insert(key.to_string(), value);
+            }
+        }
+
+        let mut config = Config::default();
+        for (key, value) in &values {
+            if let Err(reason) = config.apply(key, value) {
+                // Never echo secrets into logs
+                let shown = if SECRET_KEYS.contains(&key.as_str()) {
+                    "***"
+                } else {
+                    value
+                };
+                errors.push(format!("{} = \"{}\": {}", key, shown, reason));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            errors.sort();
+            Err(ConfigError { invalid: errors })
+        }
+    }
+
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "bind_address" => self.bind_address = value.parse().map_err(|_| "not an IP address")?,
+            "port" => self.port = parse_number(value)?,
+            "cache_path" if value.trim().is_empty() => return Err("must not be empty".to_string()),
+            "cache_path" => self.cache_path = value.to_string(),
+            "cache_max_entries" => self.cache_max_entries = parse_positive(value)?,
+            "cache_flush_interval_secs" => self.cache_flush_interval = parse_secs(value)?,
+            "default_platform" => self.default_platform = value.parse().map_err(|_| "unknown platform")?,
+            "player_data_ttl_secs" => self.ttls.player_data = parse_secs(value)?,
+            "champion_mastery_ttl_secs" => self.ttls.champion_mastery = parse_secs(value)?,
+            "mastery_list_ttl_secs" => self.ttls.mastery_list = parse_secs(value)?,
+            "riot_id_ttl_secs" => self.ttls.riot_id = parse_secs(value)?,
+            "negative_ttl_secs" => self.ttls.negative = parse_secs(value)?,
+            "player_challenges_ttl_secs" => self.ttls.player_challenges = parse_secs(value)?,
+            "challenge_percentiles_ttl_secs" => self.ttls.challenge_percentiles = parse_secs(value)?,
+            "stale_while_revalidate" => self.stale_while_revalidate = parse_bool(value)?,
+            "key_check_interval_secs" => self.key_check_interval = parse_secs(value)?,
+            "max_retries" => self.retry.max_retries = parse_number(value)?,
+            "max_queue_wait_secs" => self.retry.max_queue_wait = parse_secs(value)?,
+            "default_retry_after_secs" => self.retry.default_retry_after = parse_secs(value)?,
+            "log_level" => {
+                EnvFilter::try_new(value).map_err(|_| "not a valid log level or filter")?;
+                self.log_level = value.to_string();
+            }
+            "log_format" => {
+                self.log_format = match value.trim().to_lowercase().as_str() {
+                    "text" => LogFormat::Text,
+                    "json" => LogFormat::Json,
+                    _ => return Err("must be \"text\" or \"json\"".to_string()),
+                }
+            }
+            "riot_api_base_url" if !value.contains(ROUTING_PLACEHOLDER) => {
+                return Err(format!("must contain {}", ROUTING_PLACEHOLDER))
+            }
+            "riot_api_base_url" => self.riot_api_base_url = value.trim_end_matches('/').to_string(),
+            "riot_api_keys_file" if value.trim().is_empty() => return Err("must not be empty".to_string()),
+            "riot_api_keys_file" => self.riot_api_keys_file = Some(PathBuf::from(value.trim())),
+            "client_tokens" => self.client_tokens = parse_client_tokens(value)?,
+            "client_quota_per_minute" => self.client_quota_per_minute = parse_quota(value)?,
+            "admin_token" if value.trim().is_empty() => return Err("must not be empty".to_string()),
+            "admin_token" => self.admin_token = Some(value.trim().to_string()),
+            _ => return Err("unknown key".to_string()),
+        }
+        Ok(())
+    }
+}
+
+fn read_config_file(path: &str) -> Result<toml::Table, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("failed to read: {}", e))?;
+    contents
+        .parse::<toml::Table>()
+        .map_err(|e| format!("invalid TOML: {}", e))
+}
+
+/// Parses comma separated `name:token[:requests_per_minute]` entries
+fn parse_client_tokens(value: &str) -> Result<Vec<ClientToken>, String> {
+    let mut clients: Vec<ClientToken> = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let parts: Vec<_> = entry.split(':').collect();
+        let (name, token, quota) = match parts[..] {
+            [name, token] => (name, token, None),
+            [name, token, quota] => (name, token, Some(quota)),
+            _ => return Err("entries must look like name:token or name:token:requests_per_minute".to_string()),
+        };
+
+        if name.is_empty() || token.is_empty() {
+            return Err("client name and token must not be empty".to_string());
+        }
+        if clients.iter().any(|c| c.name == name || c.token == token) {
+            return Err(format!("client {} is not unique", name));
+        }
+
+        let requests_per_minute = quota
+            .map(|q| parse_quota(q).map_err(|e| format!("client {}: {}", name, e)))
+            .transpose()?;
+        clients.push(ClientToken {
+            name: name.to_string(),
+            token: token.to_string(),
+            requests_per_minute,
+        });
+    }
+    Ok(clients)
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err("must be true or false".to_string()),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| "not a valid number".to_string())
+}
+
+fn parse_positive(value: &str) -> Result<usize, String> {
+    match parse_number(value)? {
+        0 => Err("must be greater than zero".to_string()),
+        n => Ok(n),
+    }
+}
+
+/// Requests per minute, anything that does not fit a `u32` is refused rather than truncated
+fn parse_quota(value: &str) -> Result<u32, String> {
+    u32::try_from(parse_positive(value)?).map_err(|_| format!("must be at most {}", u32::MAX))
+}
+
+fn parse_secs(value: &str) -> Result<Duration, String> {
+    parse_positive(value).map(|secs| Duration::from_secs(secs as u64))
+}
+
+#[derive(Debug)]
+pub struct ConfigError {
+    pub invalid: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Invalid configuration:")?;
+        for entry in &self.invalid {
+            writeln!(f, "  - {}", entry)?;
+        }
+        Ok(())
+    }
+}
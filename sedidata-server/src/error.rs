@@ -0,0 +1,147 @@
+use std::{fmt, time::Duration};
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+
+use crate::model::ErrorResponse;
+
+/// Every way a request to this server can fail, each mapped to a stable error code for clients
+#[derive(Debug, Clone)]
+pub enum ServerError {
+    /// Request parameters are missing or invalid
+    InvalidRequest(String),
+    /// Riot ID does not exist (account-v1 returned 404), with the time this was cached if it was
+    AccountNotFound(Option<DateTime<Utc>>),
+    /// Any other upstream resource does not exist
+    NotFound(String),
+    /// Rate limit budget exhausted, with the time after which a retry makes sense
+    RateLimited(Duration),
+    /// Riot rejected our API key (401 / 403), usually an expired development key
+    KeyInvalid,
+    /// Riot API could not be reached or returned a server error
+    UpstreamUnavailable(String),
+    /// Riot API returned a response we could not parse
+    ParseFailed(String),
+    /// Authentication is enabled and the client sent no or an unknown token
+    Unauthorized,
+    /// Client used up its request quota, with the time until the quota resets
+    QuotaExceeded(Duration),
+    /// The server itself failed, e.g. the cache database could not be read
+    Internal(String),
+}
+
+impl ServerError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServerError::InvalidRequest(_) => "invalid_request",
+            ServerError::AccountNotFound(_) => "account_not_found",
+            ServerError::NotFound(_) => "not_found",
+            ServerError::RateLimited(_) => "rate_limited",
+            ServerError::KeyInvalid => "key_invalid",
+            ServerError::UpstreamUnavailable(_) => "upstream_unavailable",
+            ServerError::ParseFailed(_) => "parse_failed",
+            ServerError::Unauthorized => "unauthorized",
+            ServerError::QuotaExceeded(_) => "quota_exceeded",
+            ServerError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ServerError::InvalidRequest(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ServerError::AccountNotFound(_) => StatusCode::NOT_FOUND,
+            ServerError::NotFound(_) => StatusCode::NOT_FOUND,
+            ServerError::RateLimited(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ServerError::KeyInvalid => StatusCode::BAD_GATEWAY,
+            ServerError::UpstreamUnavailable(_) => StatusCode::BAD_GATEWAY,
+            ServerError::ParseFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ServerError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ServerError::QuotaExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            ServerError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// When a cached negative result was stored, `None` for errors that were not cached
+    pub fn cached_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            ServerError::AccountNotFound(cached_at) => *cached_at,
+            _ => None,
+        }
+    }
+
+    /// Whether the Riot API failed rather than the requested data not existing
+    pub fn is_upstream_failure(&self) -> bool {
+        matches!(
+            self,
+            ServerError::RateLimited(_)
+                | ServerError::KeyInvalid
+                | ServerError::UpstreamUnavailable(_)
+                | ServerError::ParseFailed(_)
+        )
+    }
+
+    /// Maps a non-success status of the Riot API for the given lookup
+    pub fn from_upstream_status(status: reqwest::StatusCode, what: &str) -> Self {
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => ServerError::KeyInvalid,
+            reqwest::StatusCode::NOT_FOUND => ServerError::NotFound(what.to_string()),
+            reqwest::StatusCode::TOO_MANY_REQUESTS => ServerError::RateLimited(Duration::from_secs(1)),
+            status => ServerError::UpstreamUnavailable(format!("Riot API returned error for {}: {}", what, status)),
+        }
+    }
+}
+
+impl fmt::Display for ServerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerError::InvalidRequest(msg) => write!(f, "{}", msg),
+            ServerError::AccountNotFound(_) => write!(f, "Riot ID does not exist"),
+            ServerError::NotFound(what) => write!(f, "No {} found", what),
+            ServerError::RateLimited(retry_after) => write!(
+                f,
+                "Riot API rate limit exhausted, retry after {}s",
+                retry_after.as_secs().max(1)
+            ),
+            ServerError::KeyInvalid => write!(f, "Riot API key was rejected"),
+            ServerError::UpstreamUnavailable(msg) => write!(f, "{}", msg),
+            ServerError::ParseFailed(msg) => write!(f, "{}", msg),
+            ServerError::Unauthorized => write!(f, "Missing or invalid API token"),
+            ServerError::QuotaExceeded(retry_after) => write!(
+                f,
+                "Request quota exceeded, retry after {}s",
+                retry_after.as_secs().max(1)
+            ),
+            ServerError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        let body = Json(ErrorResponse {
+            error: self.to_string(),
+            code: self.code().to_string(),
+            cached_at: self.cached_at(),
+        });
+
+        match self {
+            ServerError::RateLimited(retry_after) | ServerError::QuotaExceeded(retry_after) => (
+                self.status(),
+                [(header::RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+                body,
+            )
+                .into_response(),
+            ServerError::Unauthorized => (self.status(), [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response(),
+            _ => (self.status(), body).into_response(),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ServerError {
+    fn from(error: rusqlite::Error) -> Self {
+        ServerError::Internal(format!("Cache database error: {}", error))
+    }
+}
@@ -0,0 +1,1198 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    io,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use axum::{
+    extract::{MatchedPath, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Extension, Router,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::task::JoinSet;
+use tower::ServiceBuilder;
+use tower_http::{
+    cors::{Any, CorsLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tracing::{debug, error, info, info_span, instrument, warn, Instrument, Level};
+use tracing_subscriber::EnvFilter;
+
+mod api_keys;
+mod auth;
+mod cache;
+pub mod config;
+mod error;
+pub mod maintenance;
+mod metrics;
+mod model;
+mod openapi;
+mod rate_limiter;
+mod readiness;
+mod region;
+mod riot_api_client;
+mod single_flight;
+mod upstream;
+
+pub use api_keys::load_keys;
+
+use api_keys::{ApiKeyPool, KeyChanges};
+use auth::ClientAuth;
+use cache::{Cache, ChallengePercentilesCacheEntry, PlayerChallengesCacheEntry, PlayerDataCacheEntry};
+use config::{ClientToken, Config, LogFormat};
+use error::ServerError;
+use metrics::Metrics;
+use model::*;
+use readiness::Readiness;
+use region::Platform;
+use riot_api_client::RiotApiClient;
+use single_flight::SingleFlight;
+use upstream::*;
+
+const MAX_BATCH_SIZE: usize = 20;
+const DEFAULT_MATCH_COUNT: usize = 10;
+const MAX_MATCH_COUNT: usize = 20;
+const CACHE_EVICTION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const KEY_FILE_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+type PuuidFlights = SingleFlight<(Platform, String, String), Result<String, ServerError>>;
+type PlayerDataFlights = SingleFlight<(Platform, String), Result<PlayerDataCacheEntry, ServerError>>;
+type PercentileFlights = SingleFlight<Platform, Result<ChallengePercentilesCacheEntry, ServerError>>;
+
+#[derive(Clone)]
+pub struct AppState {
+    config: Arc<Config>,
+    riot_client: RiotApiClient,
+    cache: Cache,
+    metrics: Arc<Metrics>,
+    auth: Arc<ClientAuth>,
+    readiness: Arc<Readiness>,
+    puuid_flights: Arc<PuuidFlights>,
+    player_data_flights: Arc<PlayerDataFlights>,
+    percentile_flights: Arc<PercentileFlights>,
+}
+
+impl AppState {
+    pub fn new(config: Config, api_keys: Vec<String>) -> Result<Self, rusqlite::Error> {
+        let metrics = Arc::new(Metrics::new());
+        let cache = Cache::new(
+            &config.cache_path,
+            config.cache_max_entries,
+            config.ttls,
+            Arc::clone(&metrics),
+        )?;
+        let riot_client = RiotApiClient::new(
+            Arc::new(ApiKeyPool::new(api_keys)),
+            config.riot_api_base_url.clone(),
+            config.retry,
+            Arc::clone(&metrics),
+            config.default_platform,
+        );
+
+        let auth = ClientAuth::new(
+            config.client_tokens.clone(),
+            config.client_quota_per_minute,
+            config.admin_token.clone(),
+        );
+
+        Ok(Self {
+            auth: Arc::new(auth),
+            readiness: Arc::new(Readiness::new()),
+            config: Arc::new(config),
+            riot_client,
+            cache,
+            metrics,
+            puuid_flights: Arc::new(SingleFlight::new()),
+            player_data_flights: Arc::new(SingleFlight::new()),
+            percentile_flights: Arc::new(SingleFlight::new()),
+        })
+    }
+
+    /// Imports the cache of older versions, only the server does this so maintenance commands change nothing
+    pub fn migrate_legacy_cache(&self) {
+        self.cache.migrate_legacy_file(&self.config.cache_path);
+    }
+
+    /// Periodically writes buffered cache entries to the database
+    pub fn spawn_cache_flush(&self) {
+        let cache = self.cache.clone();
+        let period = self.config.cache_flush_interval;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                cache.flush().await;
+            }
+        });
+    }
+
+    /// Writes everything still buffered, called once the server stopped accepting requests
+    pub async fn shutdown(&self) {
+        self.cache.flush().await;
+        info!("Cache flushed");
+    }
+
+    /// Periodically verifies the Riot API key, starting right away
+    pub fn spawn_key_check(&self) {
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(state.config.key_check_interval);
+            loop {
+                interval.tick().await;
+                state.check_api_key().await;
+            }
+        });
+    }
+
+    /// Checks every key, including rejected ones which are added back to rotation if they work again
+    async fn check_api_key(&self) {
+        let mut results = Vec::new();
+        for key in self.riot_client.keys().all() {
+            results.push(request_platform_status(&self.riot_client, &key).await);
+        }
+
+        // One working key is enough, otherwise report why the others failed
+        let result = if results.iter().any(Result::is_ok) {
+            Ok(())
+        } else {
+            results
+                .into_iter()
+                .find(|result| !matches!(result, Err(ServerError::KeyInvalid)))
+                .unwrap_or(Err(ServerError::KeyInvalid))
+        };
+        self.readiness.record(&result);
+    }
+
+    /// Polls the configured key file and swaps in its keys when it changes
+    pub fn spawn_key_reload(&self) {
+        let Some(path) = self.config.riot_api_keys_file.clone() else {
+            return;
+        };
+        let state = self.clone();
+        tokio::spawn(async move {
+            let mut modified = modified_time(&path);
+            let mut interval = tokio::time::interval(KEY_FILE_POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let current = modified_time(&path);
+                if current == modified {
+                    continue;
+                }
+                modified = current;
+                if let Err(e) = state.reload_api_keys() {
+                    error!(
+                        "Failed to reload Riot API keys from {}, keeping the current ones: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            }
+        });
+    }
+
+    /// Replaces the Riot API keys with those in the key file, the current keys stay if it cannot be read
+    pub fn reload_api_keys(&self) -> io::Result<KeyChanges> {
+        let Some(path) = &self.config.riot_api_keys_file else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no key file configured"));
+        };
+        let keys = api_keys::read_key_file(path)?;
+        let changes = self.riot_client.keys().replace(keys);
+        info!(
+            added = changes.added,
+            removed = changes.removed,
+            "Riot API keys reloaded"
+        );
+        Ok(changes)
+    }
+
+    /// Periodically drops expired entries and enforces the cache size limit
+    pub fn spawn_cache_eviction(&self) {
+        let cache = self.cache.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CACHE_EVICTION_INTERVAL);
+            loop {
+                interval.tick().await;
+                cache.evict().await;
+            }
+        });
+    }
+}
+
+pub fn router(state: AppState) -> Router {
+    let cors = CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any);
+    // Routes that spend Riot API budget require a token if authentication is configured
+    let api = Router::new()
+        .route("/league", get(get_league_entries))
+        .route("/league/batch", post(get_league_entries_batch))
+        .route("/league/history", get(get_league_history))
+        .route("/matches", get(get_recent_matches))
+        .route("/mastery", get(get_mastery_list))
+        .route("/active-game", get(get_active_game))
+        .route("/v2/league", get(get_league_v2))
+        .route("/v2/league/batch", post(get_league_batch_v2))
+        .route("/v2/challenges", get(get_challenges_v2))
+        .route("/v2/challenges/batch", post(get_challenges_batch_v2))
+        .route("/v2/challenges/percentiles", get(get_challenge_percentiles_v2))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate));
+
+    // Cache administration, only with the admin token
+    let admin = Router::new()
+        .route("/admin/cache/stats", get(get_cache_stats))
+        .route("/admin/cache/invalidate", post(invalidate_cache))
+        .route("/admin/cache/purge", post(purge_cache))
+        .route_layer(middleware::from_fn_with_state(state.clone(), authenticate_admin));
+
+    Router::new()
+        .merge(api)
+        .merge(admin)
+        .route("/heartbeat", get(heartbeat))
+        .route("/ready", get(ready))
+        .route("/metrics", get(get_metrics))
+        .route("/openapi.json", get(get_openapi))
+        .route_layer(middleware::from_fn_with_state(state.clone(), track_metrics))
+        .layer(cors)
+        .layer(
+            // Every request gets an ID (or keeps the caller's `X-Request-Id`) that tags all its log lines
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(request_span)
+                        .on_response(DefaultOnResponse::new().level(Level::INFO)),
+                )
+                .layer(PropagateRequestIdLayer::x_request_id()),
+        )
+        .with_state(state)
+}
+
+async fn get_league_entries(
+    Query(params): Query<AccountRequest>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, ServerError> {
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+    info!(name = %params.name, tagline = %params.tagline, %platform, "League request received");
+
+    let lookup = lookup_league_entries(&params, &state).await?;
+    Ok(Json(lookup.into_legacy()))
+}
+
+async fn get_league_v2(
+    Query(params): Query<AccountRequest>,
+    State(state): State<AppState>,
+) -> Result<Json<LeagueResponse>, ServerError> {
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+    info!(name = %params.name, tagline = %params.tagline, %platform, "League v2 request received");
+
+    let lookup = lookup_league_entries(&params, &state).await?;
+    Ok(Json(lookup.into_v2()))
+}
+
+async fn get_league_entries_batch(
+    State(state): State<AppState>,
+    client: Option<Extension<ClientToken>>,
+    Json(players): Json<Vec<AccountRequest>>,
+) -> Result<impl IntoResponse, ServerError> {
+    info!(players = players.len(), "Batch request received");
+
+    let results = lookup_league_batch(players, client.as_deref(), &state).await?;
+    let results: Vec<_> = results
+        .into_iter()
+        .map(|(params, result)| {
+            let (data, error) = match result {
+                Ok(lookup) => (Some(lookup.into_legacy()), None),
+                Err(e) => (None, Some(BatchPlayerError::from(&e))),
+            };
+            BatchPlayerResponse {
+                name: params.name,
+                tagline: params.tagline,
+                data,
+                error,
+            }
+        })
+        .collect();
+    Ok(Json(results))
+}
+
+async fn get_league_batch_v2(
+    State(state): State<AppState>,
+    client: Option<Extension<ClientToken>>,
+    Json(players): Json<Vec<AccountRequest>>,
+) -> Result<Json<Vec<LeagueBatchEntry>>, ServerError> {
+    info!(players = players.len(), "Batch v2 request received");
+
+    let results = lookup_league_batch(players, client.as_deref(), &state).await?;
+    let results = results
+        .into_iter()
+        .map(|(params, result)| {
+            let (data, error) = match result {
+                Ok(lookup) => (Some(lookup.into_v2()), None),
+                Err(e) => (None, Some(BatchPlayerError::from(&e))),
+            };
+            LeagueBatchEntry {
+                name: params.name,
+                tagline: params.tagline,
+                data,
+                error,
+            }
+        })
+        .collect();
+    Ok(Json(results))
+}
+
+async fn lookup_league_batch(
+    players: Vec<AccountRequest>,
+    client: Option<&ClientToken>,
+    state: &AppState,
+) -> Result<Vec<(AccountRequest, Result<LeagueLookup, ServerError>)>, ServerError> {
+    lookup_batch(players, client, state, |params, state| async move {
+        lookup_league_entries(&params, &state).await
+    })
+    .await
+}
+
+/// Looks up all players concurrently, results are in request order.
+/// Every player counts against the quota of the client, if the server requires one
+async fn lookup_batch<P, T, F, Fut>(
+    players: Vec<P>,
+    client: Option<&ClientToken>,
+    state: &AppState,
+    lookup: F,
+) -> Result<Vec<(P, Result<T, ServerError>)>, ServerError>
+where
+    P: Clone,
+    T: Send + 'static,
+    F: Fn(P, AppState) -> Fut,
+    Fut: Future<Output = Result<T, ServerError>> + Send + 'static,
+{
+    // Return 422 if batch is too large
+    if players.len() > MAX_BATCH_SIZE {
+        return Err(ServerError::InvalidRequest(format!(
+            "batch must not contain more than {} players",
+            MAX_BATCH_SIZE
+        )));
+    }
+
+    // The request itself was counted by `authenticate`, every further player costs one more
+    if let Some(client) = client {
+        let additional = players.len().saturating_sub(1) as u32;
+        state.auth.check_quota(client, additional).inspect_err(|_| {
+            warn!(client = %client.name, players = players.len(), "Batch exceeds the client's request quota");
+        })?;
+    }
+
+    // Resolve all players concurrently, remembering their position in the request
+    let mut tasks = JoinSet::new();
+    for (index, params) in players.iter().enumerate() {
+        let lookup = lookup(params.clone(), state.clone());
+        tasks.spawn(async move { (index, lookup.await) }.in_current_span());
+    }
+
+    let mut results: Vec<Option<Result<T, ServerError>>> = players.iter().map(|_| None).collect();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, result)) => results[index] = Some(result),
+            Err(e) => error!(error = %e, "Batch lookup task failed"),
+        }
+    }
+
+    // A player whose lookup panicked still gets an entry, clients match entries by position
+    Ok(players
+        .into_iter()
+        .zip(results)
+        .map(|(params, result)| {
+            let result =
+                result.unwrap_or_else(|| Err(ServerError::Internal("lookup of this player failed".to_string())));
+            (params, result)
+        })
+        .collect())
+}
+
+async fn lookup_league_entries(params: &AccountRequest, state: &AppState) -> Result<LeagueLookup, ServerError> {
+    validate_name(&params.name)?;
+    let champion = params.champion.as_deref().map(parse_champion).transpose()?;
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+
+    // 1) Resolve PUUID
+    let puuid = get_or_request_puuid(platform, &params.name, &params.tagline, state).await?;
+
+    // 2) Resolve player data (cached or fresh)
+    let (player_data, player_freshness) = get_or_request_player_data(platform, &puuid, state).await?;
+
+    // 3) Resolve optional champion mastery
+    let champion_mastery = if let Some(champion) = champion {
+        Some(get_or_request_champion_mastery(platform, &puuid, champion, state).await?)
+    } else {
+        None
+    };
+
+    // 4) Resolve the current Riot ID, the requested one may differ in capitalization
+    let riot_id = get_or_request_riot_id(platform, &puuid, state).await?;
+
+    Ok(LeagueLookup {
+        riot_id,
+        level: player_data.level,
+        ranked_stats: player_data.ranked_stats,
+        player_freshness,
+        champion_mastery,
+    })
+}
+
+async fn get_challenges_v2(
+    Query(params): Query<ChallengesRequest>,
+    State(state): State<AppState>,
+) -> Result<Json<ChallengesResponse>, ServerError> {
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+    info!(name = %params.name, tagline = %params.tagline, %platform, "Challenges request received");
+
+    Ok(Json(lookup_challenges(&params, &state).await?))
+}
+
+async fn get_challenges_batch_v2(
+    State(state): State<AppState>,
+    client: Option<Extension<ClientToken>>,
+    Json(players): Json<Vec<ChallengesRequest>>,
+) -> Result<Json<Vec<ChallengesBatchEntry>>, ServerError> {
+    info!(players = players.len(), "Challenges batch request received");
+
+    let results = lookup_batch(players, client.as_deref(), &state, |params, state| async move {
+        lookup_challenges(&params, &state).await
+    })
+    .await?;
+    let results = results
+        .into_iter()
+        .map(|(params, result)| {
+            let (data, error) = match result {
+                Ok(challenges) => (Some(challenges), None),
+                Err(e) => (None, Some(BatchPlayerError::from(&e))),
+            };
+            ChallengesBatchEntry {
+                name: params.name,
+                tagline: params.tagline,
+                data,
+                error,
+            }
+        })
+        .collect();
+    Ok(Json(results))
+}
+
+async fn lookup_challenges(params: &ChallengesRequest, state: &AppState) -> Result<ChallengesResponse, ServerError> {
+    validate_name(&params.name)?;
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+
+    // 1) Resolve PUUID
+    let puuid = get_or_request_puuid(platform, &params.name, &params.tagline, state).await?;
+
+    // 2) Resolve challenge progress (cached or fresh)
+    let (entry, mut freshness) = get_or_request_player_challenges(platform, &puuid, state).await?;
+    freshness.negative = entry.challenges.is_empty();
+
+    // 3) Resolve the current Riot ID, the requested one may differ in capitalization
+    let riot_id = get_or_request_riot_id(platform, &puuid, state).await?;
+
+    Ok(ChallengesResponse {
+        riot_id,
+        total_points: entry.total_points,
+        category_points: entry.category_points,
+        challenges: entry.challenges,
+        freshness,
+    })
+}
+
+async fn get_challenge_percentiles_v2(
+    Query(params): Query<PercentilesRequest>,
+    State(state): State<AppState>,
+) -> Result<Json<ChallengePercentilesResponse>, ServerError> {
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+    info!(%platform, "Challenge percentiles request received");
+
+    let (entry, freshness) = get_or_request_challenge_percentiles(platform, &state).await?;
+    Ok(Json(ChallengePercentilesResponse {
+        percentiles: entry.percentiles,
+        freshness,
+    }))
+}
+
+async fn get_league_history(
+    Query(params): Query<LeagueHistoryRequest>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, ServerError> {
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+    info!(name = %params.name, tagline = %params.tagline, %platform, "League history request received");
+
+    validate_name(&params.name)?;
+    let since = match params.since {
+        Some(since) => Some(
+            DateTime::from_timestamp(since, 0)
+                .ok_or_else(|| ServerError::InvalidRequest("since is not a valid timestamp".to_string()))?,
+        ),
+        None => None,
+    };
+
+    // 1) Resolve PUUID
+    let puuid = get_or_request_puuid(platform, &params.name, &params.tagline, &state).await?;
+
+    // 2) Refresh player data if outdated, which records a snapshot if anything changed
+    get_or_request_player_data(platform, &puuid, &state).await?;
+
+    // 3) Group stored snapshots by queue
+    let mut queues: BTreeMap<String, Vec<LeagueSnapshot>> = BTreeMap::new();
+    for (queue_type, snapshot) in state.cache.get_league_history(platform, &puuid, since).await {
+        if params.queue.as_ref().is_some_and(|queue| *queue != queue_type) {
+            continue;
+        }
+        queues.entry(queue_type).or_default().push(snapshot);
+    }
+
+    Ok(Json(LeagueHistoryResponse { queues }))
+}
+
+async fn get_recent_matches(
+    Query(params): Query<MatchesRequest>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, ServerError> {
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+    info!(name = %params.name, tagline = %params.tagline, %platform, "Match history request received");
+
+    validate_name(&params.name)?;
+    let count = params.count.unwrap_or(DEFAULT_MATCH_COUNT).clamp(1, MAX_MATCH_COUNT);
+
+    // 1) Resolve PUUID
+    let puuid = get_or_request_puuid(platform, &params.name, &params.tagline, &state).await?;
+
+    // 2) Fetch latest match ids, these change with every game and are never cached
+    let match_ids = request_match_ids(platform, &puuid, count, &state.riot_client).await?;
+
+    // 3) Resolve match details concurrently, remembering their position in the list
+    let mut tasks = JoinSet::new();
+    for (index, match_id) in match_ids.into_iter().enumerate() {
+        let state = state.clone();
+        tasks.spawn(
+            async move {
+                let result = get_or_request_match(platform, &match_id, &state).await;
+                (index, result)
+            }
+            .in_current_span(),
+        );
+    }
+
+    let mut matches = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        // Like any other failed match, a failed task fails the whole list rather than leaving a gap
+        let (index, result) = joined.map_err(|e| {
+            error!(error = %e, "Match lookup task failed");
+            ServerError::Internal("match lookup failed".to_string())
+        })?;
+        matches.push((index, result?));
+    }
+    matches.sort_by_key(|(index, _)| *index);
+
+    // 4) Reduce each match to the requested player's perspective
+    let summaries: Vec<_> = matches
+        .into_iter()
+        .filter_map(|(_, info)| MatchSummary::from_match(&info, &puuid))
+        .collect();
+
+    Ok(Json(summaries))
+}
+
+#[instrument(skip(state), fields(%platform))]
+async fn get_or_request_match(platform: Platform, match_id: &str, state: &AppState) -> Result<MatchInfo, ServerError> {
+    if let Some(cached) = state.cache.get_match(platform, match_id).await {
+        return Ok(cached);
+    }
+
+    info!(match_id, "Fetching match from Riot API");
+    let info = request_match(platform, match_id, &state.riot_client).await?;
+
+    // Finished matches never change, so they are cached without expiry
+    state
+        .cache
+        .store_match(platform, match_id.to_string(), info.clone())
+        .await;
+    Ok(info)
+}
+
+async fn get_mastery_list(
+    Query(params): Query<MasteryRequest>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, ServerError> {
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+    info!(name = %params.name, tagline = %params.tagline, %platform, "Mastery request received");
+
+    validate_name(&params.name)?;
+
+    // 1) Resolve PUUID
+    let puuid = get_or_request_puuid(platform, &params.name, &params.tagline, &state).await?;
+
+    // 2) Resolve full mastery list (cached or fresh), top N is cut locally to share one cache entry
+    let (mut masteries, score) = get_or_request_mastery_list(platform, &puuid, &state).await?;
+
+    if let Some(count) = params.count {
+        masteries.truncate(count);
+    }
+
+    Ok(Json(MasteryListResponse { score, masteries }))
+}
+
+#[instrument(skip(state), fields(%platform))]
+async fn get_or_request_mastery_list(
+    platform: Platform,
+    puuid: &str,
+    state: &AppState,
+) -> Result<(Vec<ChampionMasteryDetail>, u64), ServerError> {
+    if let Some(cached) = state.cache.get_mastery_list(platform, puuid).await {
+        debug!("Mastery list found in cache");
+        return Ok((cached.masteries, cached.score));
+    }
+
+    info!("Fetching mastery list from Riot API");
+    let (masteries, score) = request_mastery_list(platform, puuid, &state.riot_client).await?;
+
+    debug!("Mastery list cached");
+    state
+        .cache
+        .store_mastery_list(platform, puuid.to_string(), score, masteries.clone())
+        .await;
+
+    Ok((masteries, score))
+}
+
+#[instrument(skip(state), fields(%platform))]
+async fn get_or_request_player_challenges(
+    platform: Platform,
+    puuid: &str,
+    state: &AppState,
+) -> Result<(PlayerChallengesCacheEntry, Freshness), ServerError> {
+    if let Some(cached) = state.cache.get_player_challenges(platform, puuid).await {
+        debug!("Player challenges found in cache");
+        let freshness = Freshness::fresh(cached.cached_at);
+        return Ok((cached, freshness));
+    }
+
+    info!("Fetching player challenges from Riot API");
+    let challenges = match request_player_challenges(platform, puuid, &state.riot_client).await {
+        Ok(challenges) => challenges,
+        Err(e) if e.is_upstream_failure() => {
+            return match state.cache.get_expired_player_challenges(platform, puuid).await {
+                Some(expired) => {
+                    warn!(error = %e, "Riot API failed, serving expired player challenges");
+                    let freshness = Freshness::stale(expired.cached_at);
+                    Ok((expired, freshness))
+                }
+                None => Err(e),
+            };
+        }
+        Err(e) => return Err(e),
+    };
+
+    debug!("Player challenges cached");
+    let entry = PlayerChallengesCacheEntry {
+        total_points: challenges.total_points.into(),
+        category_points: challenges
+            .category_points
+            .into_iter()
+            .map(|(category, points)| (category, points.into()))
+            .collect(),
+        challenges: challenges.challenges.into_iter().map(PlayerChallenge::from).collect(),
+        cached_at: Utc::now(),
+    };
+    state
+        .cache
+        .store_player_challenges(platform, puuid.to_string(), entry.clone())
+        .await;
+
+    let freshness = Freshness::fresh(entry.cached_at);
+    Ok((entry, freshness))
+}
+
+#[instrument(skip(state), fields(%platform))]
+async fn get_or_request_challenge_percentiles(
+    platform: Platform,
+    state: &AppState,
+) -> Result<(ChallengePercentilesCacheEntry, Freshness), ServerError> {
+    if let Some(cached) = state.cache.get_challenge_percentiles(platform).await {
+        debug!("Challenge percentiles found in cache");
+        let freshness = Freshness::fresh(cached.cached_at);
+        return Ok((cached, freshness));
+    }
+
+    // Every client asks for the same percentiles, concurrent lookups share a single upstream request
+    let fetched = state
+        .percentile_flights
+        .run(platform, || async {
+            // A lookup that just finished may have stored them after our cache check
+            if let Some(cached) = state.cache.peek_challenge_percentiles(platform).await {
+                return Ok(cached);
+            }
+
+            info!("Fetching challenge percentiles from Riot API");
+            let percentiles = request_challenge_percentiles(platform, &state.riot_client).await?;
+
+            debug!("Challenge percentiles cached");
+            let entry = ChallengePercentilesCacheEntry {
+                percentiles,
+                cached_at: Utc::now(),
+            };
+            state.cache.store_challenge_percentiles(platform, entry.clone()).await;
+            Ok(entry)
+        })
+        .await;
+
+    match fetched {
+        Ok(entry) => {
+            let freshness = Freshness::fresh(entry.cached_at);
+            Ok((entry, freshness))
+        }
+        Err(e) if e.is_upstream_failure() => match state.cache.get_expired_challenge_percentiles(platform).await {
+            Some(expired) => {
+                warn!(error = %e, "Riot API failed, serving expired challenge percentiles");
+                let freshness = Freshness::stale(expired.cached_at);
+                Ok((expired, freshness))
+            }
+            None => Err(e),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+async fn get_active_game(
+    Query(params): Query<ActiveGameRequest>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, ServerError> {
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+    info!(name = %params.name, tagline = %params.tagline, %platform, "Active game request received");
+
+    validate_name(&params.name)?;
+
+    // 1) Resolve PUUID
+    let puuid = get_or_request_puuid(platform, &params.name, &params.tagline, &state).await?;
+
+    // 2) Fetch current game, never cached as it changes every second (404 if not in game)
+    let game = request_active_game(platform, &puuid, &state.riot_client).await?;
+
+    Ok(Json(ActiveGameResponse::from_game(game, &puuid)))
+}
+
+async fn get_cache_stats(State(state): State<AppState>) -> Result<impl IntoResponse, ServerError> {
+    Ok(Json(state.cache.stats().await?))
+}
+
+/// Forgets everything cached about one player, without any Riot API call
+async fn invalidate_cache(
+    State(state): State<AppState>,
+    Json(params): Json<InvalidateRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let platform = params.platform.unwrap_or(state.config.default_platform);
+
+    let puuid = match (&params.puuid, &params.name, &params.tagline) {
+        (Some(puuid), None, None) => Some(puuid.clone()),
+        (None, Some(name), Some(tagline)) => {
+            validate_name(name)?;
+            let puuid = state.cache.peek_puuid(platform, name, tagline).await;
+            // Drop the Riot ID even if nothing else is known about the player
+            state.cache.remove_puuid(platform, name, tagline).await;
+            state.cache.remove_missing_account(platform, name, tagline).await;
+            puuid
+        }
+        _ => {
+            return Err(ServerError::InvalidRequest(
+                "either puuid or name and tagline are required".to_string(),
+            ))
+        }
+    };
+
+    let removed = match &puuid {
+        Some(puuid) => state.cache.remove_player(platform, puuid).await?,
+        None => 0,
+    };
+    info!(?puuid, %platform, removed, "Cache invalidated for player");
+
+    Ok(Json(InvalidateResponse { puuid, removed }))
+}
+
+async fn purge_cache(State(state): State<AppState>) -> Result<impl IntoResponse, ServerError> {
+    let removed = state.cache.purge_expired().await?;
+    info!(
+        removed = removed.values().sum::<usize>(),
+        "Expired cache entries purged"
+    );
+    Ok(Json(PurgeResponse { removed }))
+}
+
+#[instrument(skip(state), fields(%platform))]
+async fn get_or_request_puuid(
+    platform: Platform,
+    name: &str,
+    tagline: &str,
+    state: &AppState,
+) -> Result<String, ServerError> {
+    if let Some(cached_puuid) = state.cache.get_puuid(platform, name, tagline).await {
+        // The account may have been renamed since, which frees the Riot ID for someone else
+        match get_or_request_riot_id(platform, &cached_puuid, state).await {
+            Ok(riot_id) if riot_id.matches(name, tagline) => {
+                debug!("PUUID found in cache");
+                return Ok(cached_puuid);
+            }
+            Ok(riot_id) => {
+                info!(current = %riot_id, "Cached account was renamed, resolving Riot ID again");
+                state.cache.remove_puuid(platform, name, tagline).await;
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to verify Riot ID of cached PUUID, using it anyway");
+                return Ok(cached_puuid);
+            }
+        }
+    }
+
+    if let Some(cached_at) = state.cache.get_missing_account(platform, name, tagline).await {
+        debug!("Riot ID is cached as unknown");
+        return Err(ServerError::AccountNotFound(Some(cached_at)));
+    }
+
+    // Concurrent lookups of the same Riot ID share a single upstream request
+    let (normalized_name, normalized_tagline) = normalize_riot_id(name, tagline);
+    let key = (platform, normalized_name, normalized_tagline);
+    state
+        .puuid_flights
+        .run(key, || async {
+            // A lookup that just finished may have stored the result after our cache checks
+            if let Some(cached_puuid) = state.cache.peek_puuid(platform, name, tagline).await {
+                return Ok(cached_puuid);
+            }
+            if let Some(cached_at) = state.cache.peek_missing_account(platform, name, tagline).await {
+                return Err(ServerError::AccountNotFound(Some(cached_at)));
+            }
+
+            info!("Fetching PUUID from Riot API");
+            let account = match request_puuid(platform, name, tagline, &state.riot_client).await {
+                Ok(account) => account,
+                Err(ServerError::AccountNotFound(_)) => {
+                    // Only a definite 404 is remembered, transient failures are retried on the next lookup
+                    debug!("Unknown Riot ID cached");
+                    state.cache.store_missing_account(platform, name, tagline).await;
+                    return Err(ServerError::AccountNotFound(None));
+                }
+                Err(e) => return Err(e),
+            };
+
+            debug!("PUUID cached");
+            state
+                .cache
+                .store_puuid(platform, name, tagline, account.puuid.clone())
+                .await;
+            state
+                .cache
+                .store_riot_id(platform, account.puuid.clone(), account.riot_id())
+                .await;
+            Ok(account.puuid)
+        })
+        .await
+}
+
+#[instrument(skip(state), fields(%platform))]
+async fn get_or_request_riot_id(platform: Platform, puuid: &str, state: &AppState) -> Result<RiotId, ServerError> {
+    if let Some(riot_id) = state.cache.get_riot_id(platform, puuid).await {
+        debug!("Riot ID found in cache");
+        return Ok(riot_id);
+    }
+
+    info!("Fetching Riot ID from Riot API");
+    let riot_id = request_riot_id(platform, puuid, &state.riot_client).await?;
+
+    debug!("Riot ID cached");
+    state
+        .cache
+        .store_riot_id(platform, puuid.to_string(), riot_id.clone())
+        .await;
+    Ok(riot_id)
+}
+
+#[instrument(skip(state), fields(%platform))]
+async fn get_or_request_player_data(
+    platform: Platform,
+    puuid: &str,
+    state: &AppState,
+) -> Result<(PlayerDataCacheEntry, Freshness), ServerError> {
+    if let Some(cached) = state.cache.get_player_data(platform, puuid).await {
+        debug!("Player data found in cache");
+        let freshness = Freshness::fresh(cached.cached_at);
+        return Ok((cached, freshness));
+    }
+
+    let expired = state.cache.get_expired_player_data(platform, puuid).await;
+    if let Some(expired) = expired.clone().filter(|_| state.config.stale_while_revalidate) {
+        debug!("Serving expired player data while refreshing it");
+        let (state, puuid) = (state.clone(), puuid.to_string());
+        tokio::spawn(
+            async move {
+                if let Err(e) = refresh_player_data(platform, &puuid, &state).await {
+                    warn!(error = %e, "Background refresh of player data failed");
+                }
+            }
+            .in_current_span(),
+        );
+        let freshness = Freshness::stale(expired.cached_at);
+        return Ok((expired, freshness));
+    }
+
+    match refresh_player_data(platform, puuid, state).await {
+        Ok(fetched) => {
+            let freshness = Freshness::fresh(fetched.cached_at);
+            Ok((fetched, freshness))
+        }
+        Err(e) if e.is_upstream_failure() => match expired {
+            Some(expired) => {
+                warn!(error = %e, "Riot API failed, serving expired player data");
+                let freshness = Freshness::stale(expired.cached_at);
+                Ok((expired, freshness))
+            }
+            None => Err(e),
+        },
+        Err(e) => Err(e),
+    }
+}
+
+async fn refresh_player_data(
+    platform: Platform,
+    puuid: &str,
+    state: &AppState,
+) -> Result<PlayerDataCacheEntry, ServerError> {
+    // Concurrent lookups of the same PUUID share a single pair of upstream requests
+    state
+        .player_data_flights
+        .run((platform, puuid.to_string()), || async {
+            // A lookup that just finished may have stored it after our cache check
+            if let Some(cached) = state.cache.peek_player_data(platform, puuid).await {
+                return Ok(cached);
+            }
+
+            info!("Fetching fresh player data from Riot API");
+            let (entries, level) = request_player_data(platform, puuid, &state.riot_client).await?;
+            state
+                .cache
+                .record_league_snapshots(platform, puuid.to_string(), entries.clone())
+                .await;
+
+            debug!("Player data cached");
+            let entry = PlayerDataCacheEntry {
+                level,
+                ranked_stats: entries,
+                cached_at: Utc::now(),
+            };
+            state
+                .cache
+                .store_player_data(platform, puuid.to_string(), entry.clone())
+                .await;
+            Ok(entry)
+        })
+        .await
+}
+
+#[instrument(skip(state), fields(%platform))]
+async fn get_or_request_champion_mastery(
+    platform: Platform,
+    puuid: &str,
+    champion: u32,
+    state: &AppState,
+) -> Result<(ChampionMastery, Freshness), ServerError> {
+    let champion_key = champion.to_string();
+    if let Some(cached) = state.cache.get_champion_mastery(platform, puuid, &champion_key).await {
+        debug!("Champion mastery found in cache");
+        return Ok((cached.mastery, Freshness::fresh(cached.cached_at)));
+    }
+
+    info!("Fetching champion mastery from Riot API");
+    let mastery = match request_champion_mastery(platform, puuid, champion, &state.riot_client).await {
+        Ok(mastery) => mastery,
+        Err(e) if e.is_upstream_failure() => {
+            return match state
+                .cache
+                .get_expired_champion_mastery(platform, puuid, &champion_key)
+                .await
+            {
+                Some(expired) => {
+                    warn!(error = %e, "Riot API failed, serving expired champion mastery");
+                    Ok((expired.mastery, Freshness::stale(expired.cached_at)))
+                }
+                None => Err(e),
+            };
+        }
+        Err(e) => return Err(e),
+    };
+
+    debug!("Champion mastery cached");
+    state
+        .cache
+        .store_champion_mastery(platform, puuid.to_string(), champion_key, mastery.clone())
+        .await;
+
+    Ok((mastery, Freshness::fresh(Utc::now())))
+}
+
+/// Return 422 unless the champion is a numeric champion id, it becomes part of the upstream path
+fn parse_champion(champion: &str) -> Result<u32, ServerError> {
+    champion
+        .parse()
+        .map_err(|_| ServerError::InvalidRequest("champion must be a numeric champion id".to_string()))
+}
+
+/// Return 422 if name is empty
+fn validate_name(name: &str) -> Result<(), ServerError> {
+    if name.trim().is_empty() {
+        return Err(ServerError::InvalidRequest("name must not be empty".to_string()));
+    }
+    Ok(())
+}
+
+async fn get_openapi() -> impl IntoResponse {
+    Json(openapi::document().clone())
+}
+
+async fn heartbeat() -> impl IntoResponse {
+    (StatusCode::OK, "OK")
+}
+
+/// Unlike `/heartbeat`, only OK if lookups can actually be answered, 503 otherwise
+async fn ready(State(state): State<AppState>) -> impl IntoResponse {
+    if state.readiness.last().checked_at.is_none() {
+        state.check_api_key().await;
+    }
+    let check = state.readiness.last();
+
+    let cache = match state.cache.check().await {
+        Ok(()) => CacheStatus::Ok,
+        Err(e) => {
+            warn!("Cache database check failed: {}", e);
+            CacheStatus::Failing
+        }
+    };
+
+    let (active_keys, total_keys) = state.riot_client.keys().counts();
+    let ready = check.key == KeyStatus::Valid
+        && check.upstream == UpstreamStatus::Reachable
+        && cache == CacheStatus::Ok
+        && active_keys > 0;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(ReadyResponse {
+            ready,
+            riot_api_key: check.key,
+            upstream: check.upstream,
+            cache,
+            checked_at: check.checked_at,
+            active_keys,
+            total_keys,
+        }),
+    )
+}
+
+pub fn init_tracing(config: &Config) {
+    // Log level was validated when loading the config
+    let filter = EnvFilter::try_new(&config.log_level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    match config.log_format {
+        LogFormat::Text => subscriber.init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+fn request_span(request: &Request) -> tracing::Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or_default();
+    info_span!(
+        "request",
+        id = %request_id,
+        method = %request.method(),
+        uri = %request.uri(),
+        client = tracing::field::Empty
+    )
+}
+
+/// Rejects requests without a valid token and enforces the client's quota
+async fn authenticate(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    if state.auth.is_enabled() {
+        let authorization = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        let client = state.auth.authenticate(authorization).inspect_err(|_| {
+            warn!("Rejected request without valid API token");
+        })?;
+        tracing::Span::current().record("client", client.name.as_str());
+        state.auth.check_quota(client, 1).inspect_err(|_| {
+            warn!(client = %client.name, "Client exceeded its request quota");
+        })?;
+        // Batch handlers charge the quota for every further player
+        let client = client.clone();
+        request.extensions_mut().insert(client);
+    }
+    Ok(next.run(request).await)
+}
+
+async fn authenticate_admin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    state.auth.authenticate_admin(authorization).inspect_err(|_| {
+        warn!("Rejected admin request without valid admin token");
+    })?;
+    Ok(next.run(request).await)
+}
+
+/// Records count and latency of every routed request, labelled with the route pattern
+async fn track_metrics(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    state
+        .metrics
+        .record_request(&route, response.status().as_u16(), start.elapsed());
+    response
+}
+
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    state.cache.update_size_metrics().await;
+    let (active_keys, total_keys) = state.riot_client.keys().counts();
+    state.metrics.set_api_keys(active_keys, total_keys);
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
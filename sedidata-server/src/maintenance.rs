@@ -0,0 +1,71 @@
+//! Cache maintenance without a running server, used by the `cache` subcommand
+
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use crate::{
+    cache::{Cache, CacheExport, ImportSummary},
+    config::Config,
+    model::CacheStats,
+};
+
+#[derive(Debug)]
+pub enum MaintenanceError {
+    Database(rusqlite::Error),
+    Io(std::io::Error),
+    Format(serde_json::Error),
+}
+
+impl fmt::Display for MaintenanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MaintenanceError::Database(e) => write!(f, "Cache database error: {}", e),
+            MaintenanceError::Io(e) => write!(f, "Failed to access file: {}", e),
+            MaintenanceError::Format(e) => write!(f, "Invalid cache export: {}", e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for MaintenanceError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl From<std::io::Error> for MaintenanceError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for MaintenanceError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Format(error)
+    }
+}
+
+pub async fn stats(config: &Config) -> Result<CacheStats, MaintenanceError> {
+    let cache = Cache::open(config)?;
+    Ok(cache.stats().await?)
+}
+
+/// Removes expired entries, then applies retention and the size limit like the running server does
+pub async fn prune(config: &Config) -> Result<BTreeMap<String, usize>, MaintenanceError> {
+    let cache = Cache::open(config)?;
+    let removed = cache.purge_expired().await?;
+    cache.evict().await;
+    Ok(removed)
+}
+
+/// Writes all cache entries and the LP history as JSON, returns how many of each were written
+pub async fn export(config: &Config, path: &Path) -> Result<(usize, usize), MaintenanceError> {
+    let cache = Cache::open(config)?;
+    let data = cache.export().await?;
+    fs::write(path, serde_json::to_string(&data)?)?;
+    Ok((data.entries.len(), data.league_history.len()))
+}
+
+pub async fn import(config: &Config, path: &Path) -> Result<ImportSummary, MaintenanceError> {
+    let data: CacheExport = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let cache = Cache::open(config)?;
+    Ok(cache.import(data).await?)
+}
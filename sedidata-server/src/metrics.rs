@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use tracing::error;
+
+use crate::cache::CacheMap;
+
+/// Prometheus metrics shared by the routes, the cache and the Riot API client
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    cache_lookups: IntCounterVec,
+    cache_entries: IntGaugeVec,
+    upstream_responses: IntCounterVec,
+    upstream_retries: IntCounter,
+    api_keys: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("sedidata".to_string()), None).unwrap();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Requests handled, by route and response status"),
+            &["route", "status"],
+        )
+        .unwrap();
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time spent handling requests, by route",
+            ),
+            &["route"],
+        )
+        .unwrap();
+        let cache_lookups = IntCounterVec::new(
+            Opts::new(
+                "cache_lookups_total",
+                "Cache lookups, by cache map and result (hit or miss)",
+            ),
+            &["map", "result"],
+        )
+        .unwrap();
+        let cache_entries = IntGaugeVec::new(
+            Opts::new("cache_entries", "Entries currently cached, by cache map"),
+            &["map"],
+        )
+        .unwrap();
+        let upstream_responses = IntCounterVec::new(
+            Opts::new("upstream_responses_total", "Riot API calls, by method and status code"),
+            &["method", "status"],
+        )
+        .unwrap();
+        let upstream_retries = IntCounter::new("upstream_retries_total", "Riot API calls retried after a 429").unwrap();
+        let api_keys = IntGaugeVec::new(
+            Opts::new(
+                "riot_api_keys",
+                "Configured Riot API keys, by state (active or rejected)",
+            ),
+            &["state"],
+        )
+        .unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_request_duration.clone())).unwrap();
+        registry.register(Box::new(cache_lookups.clone())).unwrap();
+        registry.register(Box::new(cache_entries.clone())).unwrap();
+        registry.register(Box::new(upstream_responses.clone())).unwrap();
+        registry.register(Box::new(upstream_retries.clone())).unwrap();
+        registry.register(Box::new(api_keys.clone())).unwrap();
+
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            cache_lookups,
+            cache_entries,
+            upstream_responses,
+            upstream_retries,
+            api_keys,
+        }
+    }
+
+    pub fn record_request(&self, route: &str, status: u16, elapsed: Duration) {
+        self.http_requests
+            .with_label_values(&[route, &status.to_string()])
+            .inc();
+        self.http_request_duration
+            .with_label_values(&[route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_cache_lookup(&self, map: CacheMap, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.cache_lookups.with_label_values(&[map.as_str(), result]).inc();
+    }
+
+    pub fn set_cache_entries(&self, map: CacheMap, count: u64) {
+        self.cache_entries.with_label_values(&[map.as_str()]).set(count as i64);
+    }
+
+    /// Records a finished Riot API call, `status` is `None` if no response was received
+    pub fn record_upstream(&self, method: &str, status: Option<u16>) {
+        let status = status.map_or_else(|| "error".to_string(), |s| s.to_string());
+        self.upstream_responses.with_label_values(&[method, &status]).inc();
+    }
+
+    pub fn record_upstream_retry(&self) {
+        self.upstream_retries.inc();
+    }
+
+    pub fn set_api_keys(&self, active: usize, total: usize) {
+        self.api_keys.with_label_values(&["active"]).set(active as i64);
+        self.api_keys
+            .with_label_values(&["rejected"])
+            .set((total - active) as i64);
+    }
+
+    /// Renders all metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
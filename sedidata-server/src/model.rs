@@ -1,23 +1,79 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+use crate::{error::ServerError, region::Platform};
+
+pub use sedidata_common::{
+    normalize_riot_id, BatchPlayerError, CacheStatus, ChallengePercentilesResponse, ChallengePoints,
+    ChallengesBatchEntry, ChallengesResponse, ChampionMasteryStats, ErrorResponse, Freshness, KeyStatus,
+    LeagueBatchEntry, LeagueFreshness, LeagueResponse, PlayerChallenge, RankedStats, ReadyResponse, RiotId,
+    UpstreamStatus,
+};
+
+/// Riot ID to look up, with an optional champion id for mastery
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 pub struct AccountRequest {
     pub name: String,
     pub tagline: String,
     pub champion: Option<String>,
+    pub platform: Option<Platform>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatchesRequest {
+    pub name: String,
+    pub tagline: String,
+    pub count: Option<usize>,
+    pub platform: Option<Platform>,
+}
+
+/// Riot ID whose challenges are looked up
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ChallengesRequest {
+    pub name: String,
+    pub tagline: String,
+    pub platform: Option<Platform>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PercentilesRequest {
+    pub platform: Option<Platform>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MasteryRequest {
+    pub name: String,
+    pub tagline: String,
+    pub count: Option<usize>,
+    pub platform: Option<Platform>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RiotAccountResponse {
     pub puuid: String,
-
-    #[allow(dead_code)]
     pub game_name: String,
-    #[allow(dead_code)]
     pub tag_line: String,
 }
 
+impl RiotAccountResponse {
+    pub fn riot_id(&self) -> RiotId {
+        RiotId {
+            game_name: self.game_name.clone(),
+            tag_line: self.tag_line.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiotSummonerResponse {
+    pub summoner_level: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LeagueEntry {
@@ -42,7 +98,420 @@ pub struct ChampionMastery {
     pub champion_points: u64,
 }
 
+impl ChampionMastery {
+    /// Champion was never played, Riot answers 404 for those
+    pub fn is_empty(&self) -> bool {
+        self.champion_level == 0 && self.champion_points == 0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChampionMasteryDetail {
+    pub champion_id: u32,
+    pub champion_level: u64,
+    pub champion_points: u64,
+    pub last_play_time: i64,
+}
+
 #[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
+pub struct MasteryListResponse {
+    pub score: u64,
+    pub masteries: Vec<ChampionMasteryDetail>,
+}
+
+/// lol-challenges-v1 player data, `preferences` (title and banner) are not used
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiotPlayerChallengesResponse {
+    pub challenges: Vec<RiotChallengeInfo>,
+    pub total_points: RiotChallengePoints,
+    #[serde(default)]
+    pub category_points: BTreeMap<String, RiotChallengePoints>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiotChallengeInfo {
+    pub challenge_id: u64,
+    pub level: String,
+    pub value: f64,
+    pub percentile: Option<f64>,
+    /// Milliseconds since the epoch
+    pub achieved_time: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RiotChallengePoints {
+    pub level: String,
+    pub current: u64,
+    pub max: u64,
+    pub percentile: Option<f64>,
+}
+
+impl From<RiotChallengePoints> for ChallengePoints {
+    fn from(points: RiotChallengePoints) -> Self {
+        Self {
+            level: points.level,
+            current: points.current,
+            max: points.max,
+            percentile: points.percentile,
+        }
+    }
+}
+
+impl From<RiotChallengeInfo> for PlayerChallenge {
+    fn from(challenge: RiotChallengeInfo) -> Self {
+        Self {
+            challenge_id: challenge.challenge_id,
+            level: challenge.level,
+            value: challenge.value,
+            percentile: challenge.percentile,
+            achieved_at: challenge.achieved_time.and_then(DateTime::from_timestamp_millis),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RiotMatchResponse {
+    pub info: MatchInfo,
+}
+
+/// Subset of a match-v5 match, only the fields needed for summaries are kept (and cached)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchInfo {
+    pub game_id: u64,
+    pub game_creation: i64,
+    pub game_duration: u64,
+    pub queue_id: u32,
+    pub participants: Vec<MatchParticipant>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchParticipant {
+    pub puuid: String,
+    pub champion_id: u32,
+    pub champion_name: String,
+    pub team_position: String,
+    pub kills: u32,
+    pub deaths: u32,
+    pub assists: u32,
+    pub total_minions_killed: u32,
+    pub neutral_minions_killed: u32,
+    pub win: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatchSummary {
+    pub game_id: u64,
+    pub game_creation: i64,
+    pub duration_seconds: u64,
+    pub queue_id: u32,
+    pub champion_id: u32,
+    pub champion_name: String,
+    pub position: String,
+    pub kills: u32,
+    pub deaths: u32,
+    pub assists: u32,
+    pub cs: u32,
+    pub win: bool,
+}
+
+impl MatchSummary {
+    pub fn from_match(info: &MatchInfo, puuid: &str) -> Option<Self> {
+        let player = info.participants.iter().find(|p| p.puuid == puuid)?;
+        Some(Self {
+            game_id: info.game_id,
+            game_creation: info.game_creation,
+            duration_seconds: info.game_duration,
+            queue_id: info.queue_id,
+            champion_id: player.champion_id,
+            champion_name: player.champion_name.clone(),
+            position: player.team_position.clone(),
+            kills: player.kills,
+            deaths: player.deaths,
+            assists: player.assists,
+            cs: player.total_minions_killed + player.neutral_minions_killed,
+            win: player.win,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LeagueHistoryRequest {
+    pub name: String,
+    pub tagline: String,
+    pub platform: Option<Platform>,
+    /// Only return this queue, e.g. `RANKED_SOLO_5x5`
+    pub queue: Option<String>,
+    /// Only return snapshots recorded at or after this unix timestamp (seconds)
+    pub since: Option<i64>,
+}
+
+/// Ranked state of one queue at the time it was first seen
+#[derive(Debug, Clone, Serialize)]
+pub struct LeagueSnapshot {
+    pub tier: String,
+    pub rank: String,
+    pub league_points: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeagueHistoryResponse {
+    /// Snapshots per queue type, oldest first
+    pub queues: BTreeMap<String, Vec<LeagueSnapshot>>,
+}
+
+/// Admin request to forget a player, either by Riot ID or by PUUID
+#[derive(Debug, Deserialize)]
+pub struct InvalidateRequest {
+    pub name: Option<String>,
+    pub tagline: Option<String>,
+    pub puuid: Option<String>,
+    pub platform: Option<Platform>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvalidateResponse {
+    /// `None` if the Riot ID was not cached
+    pub puuid: Option<String>,
+    pub removed: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurgeResponse {
+    /// Removed entries per cache map
+    pub removed: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStats {
+    pub maps: BTreeMap<String, CacheMapStats>,
+    pub league_history_snapshots: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheMapStats {
+    pub entries: u64,
+    /// Entries past their TTL, only served if the Riot API fails
+    pub expired: u64,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ActiveGameRequest {
+    pub name: String,
+    pub tagline: String,
+    pub platform: Option<Platform>,
+}
+
+/// Subset of a spectator-v5 current game
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiotActiveGameResponse {
+    pub game_id: u64,
+    pub game_mode: String,
+    /// Missing for custom games
+    pub game_queue_config_id: Option<u32>,
+    /// Epoch milliseconds, 0 while the game is still loading
+    pub game_start_time: i64,
+    /// Seconds since the game started
+    pub game_length: i64,
+    pub participants: Vec<ActiveGameParticipant>,
+    #[serde(default)]
+    pub banned_champions: Vec<ActiveGameBan>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveGameParticipant {
+    pub puuid: Option<String>,
+    #[serde(default)]
+    pub riot_id: String,
+    pub champion_id: i32,
+    pub team_id: u32,
+    pub spell1_id: u32,
+    pub spell2_id: u32,
+    #[serde(default)]
+    pub bot: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveGameBan {
+    /// -1 if the team did not ban
+    pub champion_id: i32,
+    pub team_id: u32,
+    pub pick_turn: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActiveGameResponse {
+    pub game_id: u64,
+    pub game_mode: String,
+    pub queue_id: Option<u32>,
+    pub game_start_time: i64,
+    pub game_length_seconds: i64,
+    pub participants: Vec<ActiveGamePlayer>,
+    pub bans: Vec<ActiveGameBanSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActiveGamePlayer {
+    pub riot_id: String,
+    pub champion_id: i32,
+    pub team_id: u32,
+    pub spell1_id: u32,
+    pub spell2_id: u32,
+    pub bot: bool,
+    /// Marks the player the game was requested for
+    pub is_requested_player: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActiveGameBanSummary {
+    pub champion_id: i32,
+    pub team_id: u32,
+    pub pick_turn: u32,
+}
+
+impl ActiveGameResponse {
+    pub fn from_game(game: RiotActiveGameResponse, puuid: &str) -> Self {
+        Self {
+            game_id: game.game_id,
+            game_mode: game.game_mode,
+            queue_id: game.game_queue_config_id,
+            game_start_time: game.game_start_time,
+            game_length_seconds: game.game_length,
+            participants: game
+                .participants
+                .into_iter()
+                .map(|p| ActiveGamePlayer {
+                    is_requested_player: p.puuid.as_deref() == Some(puuid),
+                    riot_id: p.riot_id,
+                    champion_id: p.champion_id,
+                    team_id: p.team_id,
+                    spell1_id: p.spell1_id,
+                    spell2_id: p.spell2_id,
+                    bot: p.bot,
+                })
+                .collect(),
+            bans: game
+                .banned_champions
+                .into_iter()
+                .map(|b| ActiveGameBanSummary {
+                    champion_id: b.champion_id,
+                    team_id: b.team_id,
+                    pick_turn: b.pick_turn,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Everything resolved for a league lookup, rendered as either version of the response
+pub struct LeagueLookup {
+    pub riot_id: RiotId,
+    pub level: u64,
+    pub ranked_stats: Vec<LeagueEntry>,
+    pub player_freshness: Freshness,
+    pub champion_mastery: Option<(ChampionMastery, Freshness)>,
+}
+
+impl LeagueLookup {
+    fn freshness(&self) -> LeagueFreshness {
+        LeagueFreshness {
+            level: self.player_freshness,
+            ranked_stats: Freshness {
+                negative: self.ranked_stats.is_empty(),
+                ..self.player_freshness
+            },
+            champion_mastery: self.champion_mastery.as_ref().map(|(mastery, freshness)| Freshness {
+                negative: mastery.is_empty(),
+                ..*freshness
+            }),
+        }
+    }
+
+    /// Response of `/league`, field for field what older TUI versions parse
+    pub fn into_legacy(self) -> LegacyLeagueResponse {
+        let freshness = self.freshness();
+        let stale = freshness.ranked_stats.stale || freshness.champion_mastery.is_some_and(|f| f.stale);
+        let cached_at = freshness.champion_mastery.map_or(self.player_freshness.cached_at, |f| {
+            f.cached_at.min(self.player_freshness.cached_at)
+        });
+
+        LegacyLeagueResponse {
+            riot_id: self.riot_id.to_string(),
+            level: self.level,
+            ranked_stats: self.ranked_stats,
+            champion_mastery: self.champion_mastery.map(|(mastery, _)| mastery),
+            stale,
+            cached_at,
+            freshness,
+        }
+    }
+
+    pub fn into_v2(self) -> LeagueResponse {
+        let freshness = self.freshness();
+        LeagueResponse {
+            riot_id: self.riot_id,
+            level: self.level,
+            ranked_stats: self
+                .ranked_stats
+                .into_iter()
+                .map(|entry| RankedStats {
+                    queue_type: entry.queue_type,
+                    tier: entry.tier,
+                    rank: entry.rank,
+                    league_points: entry.league_points,
+                    wins: entry.wins,
+                    losses: entry.losses,
+                })
+                .collect(),
+            champion_mastery: self.champion_mastery.map(|(mastery, _)| ChampionMasteryStats {
+                level: mastery.champion_level,
+                points: mastery.champion_points,
+            }),
+            freshness,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LegacyLeagueResponse {
+    pub riot_id: String,
+    pub level: u64,
+    pub ranked_stats: Vec<LeagueEntry>,
+    pub champion_mastery: Option<ChampionMastery>,
+    pub stale: bool,
+    pub cached_at: DateTime<Utc>,
+    pub freshness: LeagueFreshness,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchPlayerResponse {
+    pub name: String,
+    pub tagline: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<LegacyLeagueResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<BatchPlayerError>,
+}
+
+impl From<&ServerError> for BatchPlayerError {
+    fn from(error: &ServerError) -> Self {
+        Self {
+            status: error.status().as_u16(),
+            code: error.code().to_string(),
+            error: error.to_string(),
+            cached_at: error.cached_at(),
+        }
+    }
 }
@@ -0,0 +1,159 @@
+use std::sync::OnceLock;
+
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    model::{
+        AccountRequest, ChallengePercentilesResponse, ChallengesBatchEntry, ChallengesRequest, ChallengesResponse,
+        ErrorResponse, LeagueBatchEntry, LeagueResponse, ReadyResponse,
+    },
+    region::Platform,
+};
+
+/// OpenAPI document of the versioned endpoints, schemas are generated from the shared response types
+pub fn document() -> &'static Value {
+    static DOCUMENT: OnceLock<Value> = OnceLock::new();
+    DOCUMENT.get_or_init(build)
+}
+
+fn build() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let league = generator.subschema_for::<LeagueResponse>();
+    let batch_request = generator.subschema_for::<Vec<AccountRequest>>();
+    let batch = generator.subschema_for::<Vec<LeagueBatchEntry>>();
+    let error = generator.subschema_for::<ErrorResponse>();
+    let platform = generator.subschema_for::<Platform>();
+    let ready = generator.subschema_for::<ReadyResponse>();
+    let challenges = generator.subschema_for::<ChallengesResponse>();
+    let challenges_batch_request = generator.subschema_for::<Vec<ChallengesRequest>>();
+    let challenges_batch = generator.subschema_for::<Vec<ChallengesBatchEntry>>();
+    let percentiles = generator.subschema_for::<ChallengePercentilesResponse>();
+    let schemas: Map<String, Value> = generator
+        .take_definitions()
+        .into_iter()
+        .map(|(name, schema)| (name, json!(schema)))
+        .collect();
+
+    let errors = error_responses(&json!(error));
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "sedidata-server",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Riot API proxy for sedidata. Unversioned routes such as `/league` keep their original format."
+        },
+        "paths": {
+            "/v2/league": {
+                "get": {
+                    "summary": "Level, ranked stats and optional champion mastery of a player",
+                    "parameters": [
+                        query_parameter("name", "Game name of the Riot ID", true, json!({ "type": "string" })),
+                        query_parameter("tagline", "Tagline of the Riot ID, without `#`", true, json!({ "type": "string" })),
+                        query_parameter("champion", "Champion id to include mastery for", false, json!({ "type": "string" })),
+                        query_parameter("platform", "Platform the player is on, defaults to the server setting", false, json!(platform)),
+                    ],
+                    "responses": with_success(&errors, "Player found", json!(league)),
+                }
+            },
+            "/v2/league/batch": {
+                "post": {
+                    "summary": "Several `/v2/league` lookups at once, failures are reported per player",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": batch_request } }
+                    },
+                    "responses": with_success(&errors, "One entry per requested player, in request order", json!(batch)),
+                }
+            },
+            "/v2/challenges": {
+                "get": {
+                    "summary": "Challenge points and progress of a player",
+                    "parameters": [
+                        query_parameter("name", "Game name of the Riot ID", true, json!({ "type": "string" })),
+                        query_parameter("tagline", "Tagline of the Riot ID, without `#`", true, json!({ "type": "string" })),
+                        query_parameter("platform", "Platform the player is on, defaults to the server setting", false, json!(platform)),
+                    ],
+                    "responses": with_success(&errors, "Player found", json!(challenges)),
+                }
+            },
+            "/v2/challenges/batch": {
+                "post": {
+                    "summary": "Several `/v2/challenges` lookups at once, failures are reported per player",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": challenges_batch_request } }
+                    },
+                    "responses": with_success(&errors, "One entry per requested player, in request order", json!(challenges_batch)),
+                }
+            },
+            "/v2/challenges/percentiles": {
+                "get": {
+                    "summary": "Share of players that reached each level of every challenge",
+                    "parameters": [
+                        query_parameter("platform", "Platform to get percentiles for, defaults to the server setting", false, json!(platform)),
+                    ],
+                    "responses": with_success(&errors, "Percentiles by challenge id and level", json!(percentiles)),
+                }
+            },
+            "/ready": {
+                "get": {
+                    "summary": "Whether the Riot API key is valid and lookups can be answered",
+                    "security": [],
+                    "responses": {
+                        "200": json_response("Ready", json!(ready)),
+                        "503": json_response("Not ready, the body tells which part failed", json!(ready)),
+                    },
+                }
+            }
+        },
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "bearerAuth": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "Only required if the server has client tokens configured"
+                }
+            }
+        },
+        "security": [{}, { "bearerAuth": [] }]
+    })
+}
+
+fn query_parameter(name: &str, description: &str, required: bool, schema: Value) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "description": description,
+        "required": required,
+        "schema": schema,
+    })
+}
+
+fn error_responses(error: &Value) -> Map<String, Value> {
+    [
+        ("401", "Missing or unknown client token"),
+        ("404", "Riot ID does not exist"),
+        ("422", "Invalid request parameters"),
+        ("429", "Client quota exceeded"),
+        ("502", "Riot API unavailable or API key invalid"),
+        ("503", "Riot API rate limit exhausted"),
+    ]
+    .into_iter()
+    .map(|(status, description)| (status.to_string(), json_response(description, error.clone())))
+    .collect()
+}
+
+fn with_success(errors: &Map<String, Value>, description: &str, schema: Value) -> Value {
+    let mut responses = errors.clone();
+    responses.insert("200".to_string(), json_response(description, schema));
+    Value::Object(responses)
+}
+
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } }
+    })
+}
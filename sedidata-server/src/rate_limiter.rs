@@ -0,0 +1,265 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Limits assumed for the application before Riot reported the real ones (development key)
+const DEFAULT_APP_LIMITS: [(u32, u64); 2] = [(20, 1), (100, 120)];
+/// Longest window or block honored, longer ones from headers are clamped so deadlines cannot overflow
+const MAX_WAIT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Budget of a single limit, e.g. 100 requests per 120 seconds
+#[derive(Debug)]
+struct Window {
+    limit: u32,
+    duration: Duration,
+    count: u32,
+    started: Instant,
+}
+
+impl Window {
+    fn new(limit: u32, duration: Duration, now: Instant) -> Self {
+        Self {
+            limit,
+            duration,
+            count: 0,
+            started: now,
+        }
+    }
+
+    fn refresh(&mut self, now: Instant) {
+        if now >= self.started + self.duration {
+            self.count = 0;
+            self.started = now;
+        }
+    }
+
+    fn wait_time(&self, now: Instant) -> Option<Duration> {
+        (self.count >= self.limit).then(|| (self.started + self.duration).saturating_duration_since(now))
+    }
+}
+
+/// A set of windows that apply together, either for the whole app or a single method on one host
+#[derive(Debug, Default)]
+struct Scope {
+    windows: Vec<Window>,
+    blocked_until: Option<Instant>,
+}
+
+impl Scope {
+    fn wait_time(&mut self, now: Instant) -> Duration {
+        let blocked = self
+            .blocked_until
+            .map(|until| until.saturating_duration_since(now))
+            .unwrap_or_default();
+
+        self.windows
+            .iter_mut()
+            .filter_map(|w| {
+                w.refresh(now);
+                w.wait_time(now)
+            })
+            .fold(blocked, Duration::max)
+    }
+}
+
+/// Tracks app and method rate limits as reported by the Riot API response headers
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    scopes: Mutex<HashMap<String, Scope>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn app_key(host: &str) -> String {
+        host.to_string()
+    }
+
+    pub fn method_key(host: &str, method: &str) -> String {
+        format!("{} {}", host, method)
+    }
+
+    /// Takes one request from every given scope, or returns how long to wait until that is possible
+    pub fn try_acquire(&self, app_key: &str, method_key: &str) -> Result<(), Duration> {
+        let mut scopes = self.scopes.lock().unwrap();
+        let now = Instant::now();
+
+        let app = scopes.entry(app_key.to_string()).or_insert_with(|| Scope {
+            windows: DEFAULT_APP_LIMITS
+                .iter()
+                .map(|&(limit, secs)| Window::new(limit, Duration::from_secs(secs), now))
+                .collect(),
+            blocked_until: None,
+        });
+        let app_wait = app.wait_time(now);
+        let method_wait = scopes.entry(method_key.to_string()).or_default().wait_time(now);
+
+        let wait = app_wait.max(method_wait);
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+
+        for key in [app_key, method_key] {
+            if let Some(scope) = scopes.get_mut(key) {
+                scope.windows.iter_mut().for_each(|w| w.count += 1);
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies a limit header like `20:1,100:120` and its count header like `3:1,12:120`
+    pub fn update(&self, key: &str, limits: &str, counts: Option<&str>) {
+        let limits = parse_limit_header(limits);
+        if limits.is_empty() {
+            return;
+        }
+        let counts: HashMap<u64, u32> = counts
+            .map(parse_limit_header)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(count, secs)| (secs, count))
+            .collect();
+
+        let mut scopes = self.scopes.lock().unwrap();
+        let now = Instant::now();
+        let scope = scopes.entry(key.to_string()).or_default();
+
+        // Keep running windows so their start time is preserved, replace the rest
+        let mut windows = Vec::new();
+        for (limit, secs) in limits {
+            let count = counts.get(&secs).copied().unwrap_or(0);
+            let duration = Duration::from_secs(secs).min(MAX_WAIT);
+            let mut window = match scope.windows.iter().position(|w| w.duration == duration) {
+                Some(index) => scope.windows.swap_remove(index),
+                None => Window::new(limit, duration, now),
+            };
+            window.limit = limit;
+            window.count = window.count.max(count);
+            windows.push(window);
+        }
+        scope.windows = windows;
+    }
+
+    /// Blocks a scope entirely, used when Riot answers with 429 and `Retry-After`
+    pub fn block(&self, key: &str, duration: Duration) {
+        let mut scopes = self.scopes.lock().unwrap();
+        let until = Instant::now() + duration.min(MAX_WAIT);
+        let scope = scopes.entry(key.to_string()).or_default();
+        scope.blocked_until = Some(scope.blocked_until.map_or(until, |current| current.max(until)));
+    }
+}
+
+fn parse_limit_header(header: &str) -> Vec<(u32, u64)> {
+    header
+        .split(',')
+        .filter_map(|part| {
+            let (count, secs) = part.trim().split_once(':')?;
+            Some((count.parse().ok()?, secs.parse().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APP: &str = "euw1.api.riotgames.com";
+    const METHOD: &str = "euw1.api.riotgames.com league-v4.getLeagueEntriesByPUUID";
+
+    fn acquire(limiter: &RateLimiter) -> Result<(), Duration> {
+        limiter.try_acquire(APP, METHOD)
+    }
+
+    #[test]
+    fn malformed_limit_headers_are_ignored() {
+        let limiter = RateLimiter::new();
+        limiter.update(METHOD, "garbage", Some("1:1"));
+        limiter.update(METHOD, "1:x,:5,abc", None);
+
+        assert!(acquire(&limiter).is_ok());
+        assert!(acquire(&limiter).is_ok());
+    }
+
+    #[test]
+    fn malformed_parts_do_not_drop_valid_limits() {
+        let limiter = RateLimiter::new();
+        limiter.update(METHOD, "oops,1:10", Some("nonsense"));
+
+        assert!(acquire(&limiter).is_ok());
+        assert!(acquire(&limiter).is_err());
+    }
+
+    #[test]
+    fn every_window_must_have_budget_left() {
+        let limiter = RateLimiter::new();
+        limiter.update(METHOD, "10:1,2:10", None);
+
+        assert!(acquire(&limiter).is_ok());
+        assert!(acquire(&limiter).is_ok());
+        // The short window has budget left, the long one decides how long to wait
+        let wait = acquire(&limiter).unwrap_err();
+        assert!(wait > Duration::from_secs(1) && wait <= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn count_headers_use_up_their_window() {
+        let limiter = RateLimiter::new();
+        limiter.update(METHOD, "10:1,5:10", Some("1:1,5:10"));
+
+        let wait = acquire(&limiter).unwrap_err();
+        assert!(wait > Duration::from_secs(1));
+    }
+
+    #[test]
+    fn updated_limits_keep_requests_made_in_a_running_window() {
+        let limiter = RateLimiter::new();
+        limiter.update(METHOD, "2:10", None);
+        assert!(acquire(&limiter).is_ok());
+
+        limiter.update(METHOD, "2:10", Some("0:10"));
+
+        assert!(acquire(&limiter).is_ok());
+        assert!(acquire(&limiter).is_err());
+    }
+
+    #[test]
+    fn application_scope_starts_with_development_key_limits() {
+        let limiter = RateLimiter::new();
+
+        for _ in 0..20 {
+            assert!(acquire(&limiter).is_ok());
+        }
+        assert!(acquire(&limiter).is_err());
+    }
+
+    #[test]
+    fn retry_after_blocks_only_its_scope() {
+        let limiter = RateLimiter::new();
+        limiter.block(METHOD, Duration::from_secs(30));
+
+        let wait = acquire(&limiter).unwrap_err();
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+        assert!(limiter.try_acquire(APP, "other method").is_ok());
+    }
+
+    #[test]
+    fn huge_retry_after_is_clamped() {
+        let limiter = RateLimiter::new();
+        limiter.block(METHOD, Duration::MAX);
+
+        assert!(acquire(&limiter).unwrap_err() <= MAX_WAIT);
+    }
+
+    #[test]
+    fn huge_window_is_clamped() {
+        let limiter = RateLimiter::new();
+        limiter.update(METHOD, &format!("1:{}", u64::MAX), None);
+
+        assert!(acquire(&limiter).is_ok());
+        assert!(acquire(&limiter).unwrap_err() <= MAX_WAIT);
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use tracing::{error, info};
+
+use crate::{
+    error::ServerError,
+    model::{KeyStatus, UpstreamStatus},
+};
+
+/// Outcome of the last Riot API key check, valid if at least one key works
+#[derive(Debug, Clone, Copy)]
+pub struct KeyCheck {
+    pub key: KeyStatus,
+    pub upstream: UpstreamStatus,
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+/// Remembers the last key check for `/ready`, the check itself runs periodically in the background
+pub struct Readiness {
+    last: Mutex<KeyCheck>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Self {
+            last: Mutex::new(KeyCheck {
+                key: KeyStatus::Unknown,
+                upstream: UpstreamStatus::Unknown,
+                checked_at: None,
+            }),
+        }
+    }
+
+    pub fn last(&self) -> KeyCheck {
+        *self.last.lock().unwrap()
+    }
+
+    /// Interprets the result of a key check and logs changes of the key status
+    pub fn record(&self, result: &Result<(), ServerError>) {
+        let mut last = self.last.lock().unwrap();
+        let (key, upstream) = match result {
+            Ok(()) => (KeyStatus::Valid, UpstreamStatus::Reachable),
+            Err(ServerError::KeyInvalid) => (KeyStatus::Invalid, UpstreamStatus::Reachable),
+            // Rate limited says nothing about the key, but Riot answered before
+            Err(ServerError::RateLimited(_)) => (last.key, UpstreamStatus::Reachable),
+            Err(_) => (KeyStatus::Unknown, UpstreamStatus::Unreachable),
+        };
+
+        if key != last.key {
+            match key {
+                KeyStatus::Invalid => error!("Every Riot API key was rejected, lookups fail until one is replaced"),
+                KeyStatus::Valid => info!("Riot API key check passed"),
+                KeyStatus::Unknown => {}
+            }
+        }
+        *last = KeyCheck {
+            key,
+            upstream,
+            checked_at: Some(Utc::now()),
+        };
+    }
+}
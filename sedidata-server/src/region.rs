@@ -0,0 +1,126 @@
+use std::{fmt, str::FromStr};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Platform routing value, identifies the shard a summoner plays on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Br1,
+    Eun1,
+    #[default]
+    Euw1,
+    Jp1,
+    Kr,
+    La1,
+    La2,
+    Me1,
+    Na1,
+    Oc1,
+    Ph2,
+    Ru,
+    Sg2,
+    Th2,
+    Tr1,
+    Tw2,
+    Vn2,
+}
+
+/// Regional routing value, groups multiple platforms into one cluster
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Region {
+    Americas,
+    Asia,
+    Europe,
+    Sea,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 17] = [
+        Platform::Br1,
+        Platform::Eun1,
+        Platform::Euw1,
+        Platform::Jp1,
+        Platform::Kr,
+        Platform::La1,
+        Platform::La2,
+        Platform::Me1,
+        Platform::Na1,
+        Platform::Oc1,
+        Platform::Ph2,
+        Platform::Ru,
+        Platform::Sg2,
+        Platform::Th2,
+        Platform::Tr1,
+        Platform::Tw2,
+        Platform::Vn2,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Platform::Br1 => "br1",
+            Platform::Eun1 => "eun1",
+            Platform::Euw1 => "euw1",
+            Platform::Jp1 => "jp1",
+            Platform::Kr => "kr",
+            Platform::La1 => "la1",
+            Platform::La2 => "la2",
+            Platform::Me1 => "me1",
+            Platform::Na1 => "na1",
+            Platform::Oc1 => "oc1",
+            Platform::Ph2 => "ph2",
+            Platform::Ru => "ru",
+            Platform::Sg2 => "sg2",
+            Platform::Th2 => "th2",
+            Platform::Tr1 => "tr1",
+            Platform::Tw2 => "tw2",
+            Platform::Vn2 => "vn2",
+        }
+    }
+
+    pub fn region(&self) -> Region {
+        match self {
+            Platform::Br1 | Platform::La1 | Platform::La2 | Platform::Na1 => Region::Americas,
+            Platform::Eun1 | Platform::Euw1 | Platform::Me1 | Platform::Ru | Platform::Tr1 => Region::Europe,
+            Platform::Jp1 | Platform::Kr => Region::Asia,
+            Platform::Oc1 | Platform::Ph2 | Platform::Sg2 | Platform::Th2 | Platform::Tw2 | Platform::Vn2 => {
+                Region::Sea
+            }
+        }
+    }
+
+    /// Region serving account-v1, which is not served by the SEA cluster
+    pub fn account_region(&self) -> Region {
+        match self.region() {
+            Region::Sea => Region::Asia,
+            region => region,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        Platform::ALL.into_iter().find(|p| p.as_str() == s).ok_or(())
+    }
+}
+
+impl Region {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Region::Americas => "americas",
+            Region::Asia => "asia",
+            Region::Europe => "europe",
+            Region::Sea => "sea",
+        }
+    }
+}
@@ -1,40 +1,246 @@
-use reqwest::{Client, Response};
-use tokio::time::{sleep, Duration};
+use std::{fmt, sync::Arc};
+
+use reqwest::{header::HeaderMap, Client, Response, StatusCode, Url};
+use tokio::time::{sleep, Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::{
+    api_keys::{ApiKey, ApiKeyPool},
+    config::RetryPolicy,
+    metrics::Metrics,
+    rate_limiter::RateLimiter,
+    region::Platform,
+};
+
+/// Placeholder in the base URL that is replaced by the platform or region routing value
+pub const ROUTING_PLACEHOLDER: &str = "{routing}";
 
 #[derive(Clone)]
 pub struct RiotApiClient {
     client: Client,
-    api_key: String,
+    keys: Arc<ApiKeyPool>,
+    base_url: String,
+    retry: RetryPolicy,
+    metrics: Arc<Metrics>,
+    /// Platform whose status endpoint decides whether a key is valid
+    key_check_platform: Platform,
 }
 
 impl RiotApiClient {
-    pub fn new(api_key: String) -> Self {
+    /// `base_url` contains the routing placeholder, e.g. `https://{routing}.api.riotgames.com`
+    pub fn new(
+        keys: Arc<ApiKeyPool>,
+        base_url: String,
+        retry: RetryPolicy,
+        metrics: Arc<Metrics>,
+        key_check_platform: Platform,
+    ) -> Self {
         Self {
             client: Client::new(),
-            api_key,
+            keys,
+            base_url,
+            retry,
+            metrics,
+            key_check_platform,
         }
     }
 
-    pub async fn get(&self, url: &str) -> Result<Response, reqwest::Error> {
-        const MAX_RETRIES: usize = 5;
-        const RETRY_DELAYS: [u64; 5] = [1, 2, 3, 5, 8];
+    pub fn keys(&self) -> &ApiKeyPool {
+        &self.keys
+    }
+
+    /// Host for platform scoped endpoints (league, summoner, mastery)
+    pub fn platform_host(&self, platform: Platform) -> String {
+        self.base_url.replace(ROUTING_PLACEHOLDER, platform.as_str())
+    }
+
+    /// Host for regional endpoints (match)
+    pub fn regional_host(&self, platform: Platform) -> String {
+        self.base_url.replace(ROUTING_PLACEHOLDER, platform.region().as_str())
+    }
+
+    /// Host for account-v1
+    pub fn account_host(&self, platform: Platform) -> String {
+        self.base_url
+            .replace(ROUTING_PLACEHOLDER, platform.account_region().as_str())
+    }
+
+    /// Sends a GET request for the given Riot API method (e.g. `league-v4.getLeagueEntriesByPUUID`),
+    /// using the first key in rotation with rate limit budget left
+    pub async fn get(&self, method: &str, url: &str) -> Result<Response, RiotApiError> {
+        self.send(method, url, None).await
+    }
+
+    /// Requests the platform status with one specific key, even if it was taken out of rotation.
+    /// Only a 401 / 403 from this endpoint takes a key out of rotation, any success puts it back
+    pub async fn check_key(&self, key: &Arc<ApiKey>) -> Result<Response, RiotApiError> {
+        let url = format!(
+            "{}/lol/status/v4/platform-data",
+            self.platform_host(self.key_check_platform)
+        );
+        self.send("lol-status-v4.getPlatformData", &url, Some(key)).await
+    }
+
+    /// Whether the key check confirms that a key answered with 401 / 403 elsewhere is invalid. Other
+    /// endpoints answer the same for paths the key has no access to, which says nothing about the key
+    async fn confirm_rejection(&self, key: &Arc<ApiKey>) -> bool {
+        // Boxed because the check goes through `send` as well
+        let _ = Box::pin(self.check_key(key)).await;
+        key.is_rejected()
+    }
+
+    async fn send(&self, method: &str, url: &str, pinned: Option<&Arc<ApiKey>>) -> Result<Response, RiotApiError> {
+        let host = Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let app_key = RateLimiter::app_key(&host);
+        let method_key = RateLimiter::method_key(&host, method);
 
         let mut attempts = 0;
         loop {
-            let resp = self
-                .client
-                .get(url)
-                .header("X-Riot-Token", &self.api_key)
-                .send()
-                .await?;
-
-            if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS && attempts < MAX_RETRIES {
+            let key = self.acquire(pinned, &app_key, &method_key).await?;
+
+            let start = Instant::now();
+            let resp = self.client.get(url).header("X-Riot-Token", key.secret()).send().await;
+            self.metrics
+                .record_upstream(method, resp.as_ref().ok().map(|r| r.status().as_u16()));
+            let resp = resp?;
+            debug!(method, status = resp.status().as_u16(), elapsed = ?start.elapsed(), "Riot API call finished");
+
+            update_limits(key.limiter(), resp.headers(), &app_key, &method_key);
+
+            if matches!(resp.status(), StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+                if pinned.is_some() {
+                    if key.reject() {
+                        warn!(key = %key.label(), "Riot API rejected key, taking it out of rotation");
+                    }
+                    return Ok(resp);
+                }
+                // Another key may still work, the last one passes the rejection on to the caller
+                if self.confirm_rejection(&key).await && !self.keys.rotation().is_empty() {
+                    continue;
+                }
+                return Ok(resp);
+            }
+            if resp.status().is_success() && key.restore() {
+                info!(key = %key.label(), "Riot API accepted key again, adding it back to rotation");
+            }
+
+            if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+                let retry_after = header_str(resp.headers(), "Retry-After")
+                    .and_then(|v| v.parse().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(self.retry.default_retry_after);
+
+                // Service limits are not ours to track, only app and method limits block their scope
+                let blocked = match header_str(resp.headers(), "X-Rate-Limit-Type") {
+                    Some("application") => Some(&app_key),
+                    Some("method") => Some(&method_key),
+                    _ => None,
+                };
+                if let Some(scope) = blocked {
+                    key.limiter().block(scope, retry_after);
+                }
+
+                if attempts >= self.retry.max_retries || (blocked.is_none() && retry_after > self.retry.max_queue_wait)
+                {
+                    return Err(RiotApiError::RateLimited(retry_after));
+                }
+
                 attempts += 1;
-                sleep(Duration::from_secs(RETRY_DELAYS[attempts - 1])).await;
+                warn!(
+                    method,
+                    attempt = attempts,
+                    "Riot API returned 429, retrying in {:?}",
+                    retry_after
+                );
+                self.metrics.record_upstream_retry();
+                // A blocked scope only affects this key, acquiring picks another one or waits for it
+                if blocked.is_none() {
+                    sleep(retry_after).await;
+                }
                 continue;
             }
 
             return Ok(resp);
         }
     }
+
+    /// Takes rate limit budget from the pinned key or the first key in rotation that has some left
+    async fn acquire(
+        &self,
+        pinned: Option<&Arc<ApiKey>>,
+        app_key: &str,
+        method_key: &str,
+    ) -> Result<Arc<ApiKey>, RiotApiError> {
+        // A queue wait too long to represent has no deadline at all
+        let deadline = Instant::now().checked_add(self.retry.max_queue_wait);
+        loop {
+            let keys = match pinned {
+                Some(key) => vec![Arc::clone(key)],
+                None => self.keys.rotation(),
+            };
+            if keys.is_empty() {
+                return Err(RiotApiError::NoKeyAvailable);
+            }
+
+            let mut shortest_wait = Duration::MAX;
+            for key in keys {
+                match key.limiter().try_acquire(app_key, method_key) {
+                    Ok(()) => return Ok(key),
+                    Err(wait) => shortest_wait = shortest_wait.min(wait),
+                }
+            }
+            let too_late = match (Instant::now().checked_add(shortest_wait), deadline) {
+                (Some(ready), Some(deadline)) => ready > deadline,
+                (Some(_), None) => false,
+                // Budget that is not back within any representable time is not worth waiting for
+                (None, _) => true,
+            };
+            if too_late {
+                return Err(RiotApiError::RateLimited(shortest_wait));
+            }
+            sleep(shortest_wait).await;
+        }
+    }
+}
+
+fn update_limits(limiter: &RateLimiter, headers: &HeaderMap, app_key: &str, method_key: &str) {
+    if let Some(limits) = header_str(headers, "X-App-Rate-Limit") {
+        limiter.update(app_key, limits, header_str(headers, "X-App-Rate-Limit-Count"));
+    }
+    if let Some(limits) = header_str(headers, "X-Method-Rate-Limit") {
+        limiter.update(method_key, limits, header_str(headers, "X-Method-Rate-Limit-Count"));
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+#[derive(Debug)]
+pub enum RiotApiError {
+    Request(reqwest::Error),
+    RateLimited(Duration),
+    /// Every configured key was rejected by the Riot API
+    NoKeyAvailable,
+}
+
+impl fmt::Display for RiotApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RiotApiError::Request(e) => write!(f, "{}", e),
+            RiotApiError::RateLimited(retry_after) => {
+                write!(f, "Rate limit exceeded, retry after {}s", retry_after.as_secs_f32())
+            }
+            RiotApiError::NoKeyAvailable => write!(f, "No Riot API key left in rotation"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for RiotApiError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Request(error)
+    }
 }
@@ -0,0 +1,78 @@
+use std::{collections::HashMap, future::Future, hash::Hash, sync::Mutex};
+
+use tokio::sync::broadcast;
+
+/// Deduplicates concurrent calls for the same key, so only the first caller runs the work and
+/// everyone else waiting on that key receives a clone of its result
+pub struct SingleFlight<K, V> {
+    in_flight: Mutex<HashMap<K, broadcast::Sender<V>>>,
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn run<F, Fut>(&self, key: K, work: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        let receiver = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(sender) => Some(sender.subscribe()),
+                None => {
+                    let (sender, _) = broadcast::channel(1);
+                    in_flight.insert(key.clone(), sender);
+                    None
+                }
+            }
+        };
+
+        if let Some(mut receiver) = receiver {
+            // Leader was cancelled before it finished, do the work ourselves
+            return match receiver.recv().await {
+                Ok(value) => value,
+                Err(_) => work().await,
+            };
+        }
+
+        // Removes the key even if this future is dropped, which closes the channel for waiters
+        let guard = FlightGuard {
+            in_flight: &self.in_flight,
+            key: Some(key),
+        };
+        let value = work().await;
+        if let Some(sender) = guard.finish() {
+            let _ = sender.send(value.clone());
+        }
+        value
+    }
+}
+
+struct FlightGuard<'a, K: Eq + Hash, V> {
+    in_flight: &'a Mutex<HashMap<K, broadcast::Sender<V>>>,
+    key: Option<K>,
+}
+
+impl<K: Eq + Hash, V> FlightGuard<'_, K, V> {
+    fn finish(mut self) -> Option<broadcast::Sender<V>> {
+        let key = self.key.take()?;
+        self.in_flight.lock().unwrap().remove(&key)
+    }
+}
+
+impl<K: Eq + Hash, V> Drop for FlightGuard<'_, K, V> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.in_flight.lock().unwrap().remove(&key);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+use tracing::{error, warn};
+
+use crate::{
+    api_keys::ApiKey,
+    error::ServerError,
+    model::{
+        ChampionMastery, ChampionMasteryDetail, LeagueEntry, MatchInfo, RiotAccountResponse, RiotActiveGameResponse,
+        RiotId, RiotMatchResponse, RiotPlayerChallengesResponse, RiotSummonerResponse,
+    },
+    region::Platform,
+    riot_api_client::{RiotApiClient, RiotApiError},
+};
+
+pub async fn request_puuid(
+    platform: Platform,
+    name: &str,
+    tagline: &str,
+    client: &RiotApiClient,
+) -> Result<RiotAccountResponse, ServerError> {
+    let account_url = format!(
+        "{}/riot/account/v1/accounts/by-riot-id/{}/{}",
+        client.account_host(platform),
+        encode_path_segment(name),
+        encode_path_segment(tagline)
+    );
+
+    // Request account info to get PUUID
+    let account_response = client.get("account-v1.getByRiotId", &account_url).await;
+
+    match extract_json::<RiotAccountResponse>(account_response, "account lookup").await {
+        Ok(account) => Ok(account),
+        Err(ServerError::NotFound(_)) => Err(ServerError::AccountNotFound(None)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Looks up the current Riot ID of an account, which changes when the player renames it
+pub async fn request_riot_id(platform: Platform, puuid: &str, client: &RiotApiClient) -> Result<RiotId, ServerError> {
+    let account_url = format!(
+        "{}/riot/account/v1/accounts/by-puuid/{}",
+        client.account_host(platform),
+        puuid
+    );
+
+    let account_response = client.get("account-v1.getByPuuid", &account_url).await;
+
+    match extract_json::<RiotAccountResponse>(account_response, "account lookup by PUUID").await {
+        Ok(account) => Ok(account.riot_id()),
+        Err(ServerError::NotFound(_)) => Err(ServerError::AccountNotFound(None)),
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn request_player_data(
+    platform: Platform,
+    puuid: &str,
+    client: &RiotApiClient,
+) -> Result<(Vec<LeagueEntry>, u64), ServerError> {
+    let league_url = format!(
+        "{}/lol/league/v4/entries/by-puuid/{}",
+        client.platform_host(platform),
+        puuid
+    );
+
+    let summoner_url = format!(
+        "{}/lol/summoner/v4/summoners/by-puuid/{}",
+        client.platform_host(platform),
+        puuid
+    );
+
+    // Make both requests in parallel
+    let league_future = client.get("league-v4.getLeagueEntriesByPUUID", &league_url);
+    let summoner_future = client.get("summoner-v4.getByPUUID", &summoner_url);
+
+    let (league_response, summoner_response) = tokio::join!(league_future, summoner_future);
+
+    let entries = extract_json(league_response, "league entries").await?;
+
+    // Account exists but has never played on this platform
+    let summoner = match extract_json::<RiotSummonerResponse>(summoner_response, "summoner lookup").await {
+        Err(ServerError::NotFound(_)) => return Err(ServerError::AccountNotFound(None)),
+        result => result?,
+    };
+    Ok((entries, summoner.summoner_level))
+}
+
+pub async fn request_champion_mastery(
+    platform: Platform,
+    puuid: &str,
+    champion: u32,
+    client: &RiotApiClient,
+) -> Result<ChampionMastery, ServerError> {
+    let url = format!(
+        "{}/lol/champion-mastery/v4/champion-masteries/by-puuid/{}/by-champion/{}",
+        client.platform_host(platform),
+        puuid,
+        champion
+    );
+
+    let response = client
+        .get("champion-mastery-v4.getChampionMasteryByPUUIDAndChampionId", &url)
+        .await;
+
+    match extract_json(response, "champion mastery").await {
+        // No mastery found for this champion
+        Err(ServerError::NotFound(_)) => Ok(ChampionMastery {
+            champion_level: 0,
+            champion_points: 0,
+        }),
+        result => result,
+    }
+}
+
+pub async fn request_mastery_list(
+    platform: Platform,
+    puuid: &str,
+    client: &RiotApiClient,
+) -> Result<(Vec<ChampionMasteryDetail>, u64), ServerError> {
+    let masteries_url = format!(
+        "{}/lol/champion-mastery/v4/champion-masteries/by-puuid/{}",
+        client.platform_host(platform),
+        puuid
+    );
+    let score_url = format!(
+        "{}/lol/champion-mastery/v4/scores/by-puuid/{}",
+        client.platform_host(platform),
+        puuid
+    );
+
+    // Make both requests in parallel
+    let masteries_future = client.get("champion-mastery-v4.getAllChampionMasteriesByPUUID", &masteries_url);
+    let score_future = client.get("champion-mastery-v4.getChampionMasteryScoreByPUUID", &score_url);
+
+    let (masteries_response, score_response) = tokio::join!(masteries_future, score_future);
+
+    let masteries = extract_json(masteries_response, "champion masteries").await?;
+    let score = extract_json(score_response, "mastery score").await?;
+    Ok((masteries, score))
+}
+
+pub async fn request_match_ids(
+    platform: Platform,
+    puuid: &str,
+    count: usize,
+    client: &RiotApiClient,
+) -> Result<Vec<String>, ServerError> {
+    let url = format!(
+        "{}/lol/match/v5/matches/by-puuid/{}/ids?count={}",
+        client.regional_host(platform),
+        puuid,
+        count
+    );
+
+    let response = client.get("match-v5.getMatchIdsByPUUID", &url).await;
+    extract_json(response, "match ids").await
+}
+
+pub async fn request_match(
+    platform: Platform,
+    match_id: &str,
+    client: &RiotApiClient,
+) -> Result<MatchInfo, ServerError> {
+    let url = format!("{}/lol/match/v5/matches/{}", client.regional_host(platform), match_id);
+
+    let response = client.get("match-v5.getMatch", &url).await;
+    let riot_match: RiotMatchResponse = extract_json(response, "match details").await?;
+    Ok(riot_match.info)
+}
+
+pub async fn request_active_game(
+    platform: Platform,
+    puuid: &str,
+    client: &RiotApiClient,
+) -> Result<RiotActiveGameResponse, ServerError> {
+    let url = format!(
+        "{}/lol/spectator/v5/active-games/by-summoner/{}",
+        client.platform_host(platform),
+        puuid
+    );
+
+    let response = client.get("spectator-v5.getCurrentGameInfoByPuuid", &url).await;
+    extract_json(response, "active game").await
+}
+
+pub async fn request_player_challenges(
+    platform: Platform,
+    puuid: &str,
+    client: &RiotApiClient,
+) -> Result<RiotPlayerChallengesResponse, ServerError> {
+    let url = format!(
+        "{}/lol/challenges/v1/player-data/{}",
+        client.platform_host(platform),
+        puuid
+    );
+
+    let response = client.get("lol-challenges-v1.getPlayerData", &url).await;
+    match extract_json(response, "player challenges").await {
+        // The account has never played on this platform
+        Err(ServerError::NotFound(_)) => Err(ServerError::AccountNotFound(None)),
+        result => result,
+    }
+}
+
+/// Share of players per level of every challenge, by challenge id
+pub async fn request_challenge_percentiles(
+    platform: Platform,
+    client: &RiotApiClient,
+) -> Result<BTreeMap<u64, BTreeMap<String, f64>>, ServerError> {
+    let url = format!(
+        "{}/lol/challenges/v1/challenges/percentiles",
+        client.platform_host(platform)
+    );
+
+    let response = client.get("lol-challenges-v1.getAllChallengePercentiles", &url).await;
+    extract_json(response, "challenge percentiles").await
+}
+
+/// Cheapest call that needs a valid API key, checks the given key and ignores the response itself
+pub async fn request_platform_status(client: &RiotApiClient, key: &Arc<ApiKey>) -> Result<(), ServerError> {
+    let response = client.check_key(key).await;
+    check_status(response, "platform status").map(|_| ())
+}
+
+/// Checks the upstream status and parses the body, `what` names the lookup in errors and logs
+pub async fn extract_json<T: DeserializeOwned>(
+    response: Result<Response, RiotApiError>,
+    what: &str,
+) -> Result<T, ServerError> {
+    let resp = check_status(response, what)?;
+    resp.json::<T>().await.map_err(|e| {
+        error!("Failed to parse {} response: {}", what, e);
+        ServerError::ParseFailed(format!("Failed to parse {} from Riot API", what))
+    })
+}
+
+fn check_status(response: Result<Response, RiotApiError>, what: &str) -> Result<Response, ServerError> {
+    match response {
+        Ok(resp) if resp.status().is_success() => Ok(resp),
+        Ok(resp) => {
+            let status = resp.status();
+            warn!(%status, "Riot API returned error for {}", what);
+            Err(ServerError::from_upstream_status(status, what))
+        }
+        Err(RiotApiError::RateLimited(retry_after)) => {
+            warn!("Riot API rate limit exhausted, retry after {}s", retry_after.as_secs());
+            Err(ServerError::RateLimited(retry_after))
+        }
+        Err(RiotApiError::NoKeyAvailable) => {
+            warn!("No Riot API key left for {}", what);
+            Err(ServerError::KeyInvalid)
+        }
+        Err(e) => {
+            error!("Failed to connect to Riot API for {}: {}", what, e);
+            Err(ServerError::UpstreamUnavailable(format!(
+                "Failed to connect to Riot API for {}",
+                what
+            )))
+        }
+    }
+}
+
+/// Percent-encodes a URL path segment, Riot IDs may contain spaces and non-ASCII characters
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
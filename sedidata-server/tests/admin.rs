@@ -0,0 +1,245 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+};
+use common::*;
+use sedidata_server::{config::Config, maintenance, AppState};
+use serde_json::{json, Value};
+
+const KEY: &str = "test-key";
+const ADMIN_TOKEN: &str = "admin-secret";
+
+fn with_admin(config: Config) -> Config {
+    Config {
+        admin_token: Some(ADMIN_TOKEN.to_string()),
+        ..config
+    }
+}
+
+fn admin_request(method: Method, uri: &str, token: &str, body: Option<Value>) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap()
+}
+
+/// Unique cache file in the temp directory, removed when dropped
+struct TempCache(String);
+
+impl TempCache {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("sedidata-{}-{}.db", name, std::process::id()));
+        let cache = Self(path.to_string_lossy().into_owned());
+        cache.remove();
+        cache
+    }
+
+    fn remove(&self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.0, suffix));
+        }
+    }
+}
+
+impl Drop for TempCache {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+#[tokio::test]
+async fn admin_routes_are_disabled_without_admin_token() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = send(&state, admin_request(Method::GET, "/admin/cache/stats", "", None)).await;
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "unauthorized");
+}
+
+#[tokio::test]
+async fn admin_routes_reject_wrong_token() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_admin(mock.config()), vec![KEY.to_string()]).unwrap();
+
+    let (status, _, _) = send(&state, admin_request(Method::GET, "/admin/cache/stats", "guess", None)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _, _) = send(
+        &state,
+        admin_request(Method::GET, "/admin/cache/stats", ADMIN_TOKEN, None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn stats_count_entries_per_map() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_admin(mock.config()), vec![KEY.to_string()]).unwrap();
+    get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+
+    let (status, _, body) = send(
+        &state,
+        admin_request(Method::GET, "/admin/cache/stats", ADMIN_TOKEN, None),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    for map in ["puuid", "player_data", "champion_mastery", "riot_id"] {
+        assert_eq!(body["maps"][map]["entries"], 1, "{}", map);
+        assert_eq!(body["maps"][map]["expired"], 0, "{}", map);
+    }
+    assert_eq!(body["maps"]["match"]["entries"], 0);
+    assert!(body["league_history_snapshots"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn invalidating_riot_id_forces_fresh_lookup() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_admin(mock.config()), vec![KEY.to_string()]).unwrap();
+    get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+    let upstream_calls = mock.requests().len();
+
+    let (status, _, body) = send(
+        &state,
+        admin_request(
+            Method::POST,
+            "/admin/cache/invalidate",
+            ADMIN_TOKEN,
+            Some(json!({ "name": "faker", "tagline": "kr1" })),
+        ),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["puuid"], puuid_of("Faker"));
+    // Player data, champion mastery and Riot ID, the PUUID itself is removed separately
+    assert_eq!(body["removed"], 3);
+
+    get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+    let refetched = &mock.requests()[upstream_calls..];
+    assert!(refetched.iter().any(|path| path.contains("/accounts/by-riot-id/")));
+    assert!(refetched.iter().any(|path| path.contains("/entries/by-puuid/")));
+}
+
+#[tokio::test]
+async fn invalidate_requires_riot_id_or_puuid() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_admin(mock.config()), vec![KEY.to_string()]).unwrap();
+
+    let (status, _, body) = send(
+        &state,
+        admin_request(
+            Method::POST,
+            "/admin/cache/invalidate",
+            ADMIN_TOKEN,
+            Some(json!({ "name": "Faker" })),
+        ),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "invalid_request");
+}
+
+#[tokio::test]
+async fn purge_removes_only_expired_entries() {
+    let mock = MockRiot::start().await;
+    let mut config = with_admin(mock.config());
+    config.ttls.player_data = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+
+    let (status, _, body) = send(
+        &state,
+        admin_request(Method::POST, "/admin/cache/purge", ADMIN_TOKEN, None),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["removed"]["player_data"], 1);
+    assert_eq!(body["removed"]["champion_mastery"], 0);
+}
+
+#[tokio::test]
+async fn export_and_import_round_trip() {
+    let mock = MockRiot::start().await;
+    let source = TempCache::new("export-source");
+    let target = TempCache::new("export-target");
+    let export_path = std::env::temp_dir().join(format!("sedidata-export-{}.json", std::process::id()));
+
+    let source_config = Config {
+        cache_path: source.0.clone(),
+        ..mock.config()
+    };
+    let state = AppState::new(source_config.clone(), vec![KEY.to_string()]).unwrap();
+    get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+    state.shutdown().await;
+
+    let (entries, snapshots) = maintenance::export(&source_config, &export_path).await.unwrap();
+    let target_config = Config {
+        cache_path: target.0.clone(),
+        ..mock.config()
+    };
+    let summary = maintenance::import(&target_config, &export_path).await.unwrap();
+    // A second import adds nothing, entries are not newer and snapshots are known
+    let repeated = maintenance::import(&target_config, &export_path).await.unwrap();
+    let _ = std::fs::remove_file(&export_path);
+
+    assert_eq!(entries, 4);
+    assert_eq!((summary.entries, summary.snapshots), (entries, snapshots));
+    assert_eq!((repeated.entries, repeated.snapshots), (0, 0));
+
+    let source_stats = maintenance::stats(&source_config).await.unwrap();
+    let target_stats = maintenance::stats(&target_config).await.unwrap();
+    for (map, stats) in &source_stats.maps {
+        assert_eq!(stats.entries, target_stats.maps[map].entries, "{}", map);
+    }
+}
+
+/// Cache `cache.json` of older versions, in the format without platforms in the keys
+const LEGACY_CACHE: &str = r#"{
+    "puuid_cache": [{ "key": { "first": "faker", "second": "kr1" }, "value": { "puuid": "puuid-faker" } }],
+    "player_data_cache": {},
+    "champion_mastery_cache": []
+}"#;
+
+#[tokio::test]
+async fn legacy_cache_next_to_database_is_migrated_by_the_server_only() {
+    let mock = MockRiot::start().await;
+    let dir = std::env::temp_dir().join(format!("sedidata-legacy-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let legacy = dir.join("cache.json");
+    std::fs::write(&legacy, LEGACY_CACHE).unwrap();
+    let config = Config {
+        cache_path: dir.join("cache.db").to_string_lossy().into_owned(),
+        ..mock.config()
+    };
+
+    // Maintenance commands only read the database
+    maintenance::stats(&config).await.unwrap();
+    let untouched = legacy.exists();
+
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    state.migrate_legacy_cache();
+    let migrated = (legacy.exists(), dir.join("cache.json.migrated").exists());
+    get(&state, "/league?name=Faker&tagline=KR1&platform=euw1").await;
+    drop(state);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert!(untouched);
+    assert_eq!(migrated, (false, true));
+    // The migrated PUUID is used without asking account-v1
+    let requests = mock.requests();
+    assert!(requests.contains(&"/euw1/lol/league/v4/entries/by-puuid/puuid-faker".to_string()));
+    assert!(!requests.iter().any(|path| path.contains("/accounts/by-riot-id/")));
+}
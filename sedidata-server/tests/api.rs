@@ -0,0 +1,737 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use common::*;
+use sedidata_common::{LeagueBatchEntry, LeagueResponse};
+use sedidata_server::AppState;
+use serde_json::json;
+use std::time::Duration;
+
+const KEY: &str = "test-key";
+
+#[tokio::test]
+async fn league_lookup_combines_level_ranked_stats_and_mastery() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["level"], 512);
+    assert_eq!(body["ranked_stats"].as_array().unwrap().len(), 2);
+    assert_eq!(body["ranked_stats"][0]["queueType"], "RANKED_SOLO_5x5");
+    assert_eq!(body["ranked_stats"][0]["leaguePoints"], 1204);
+    assert_eq!(body["champion_mastery"]["championLevel"], 42);
+    assert_eq!(body["champion_mastery"]["championPoints"], 487310);
+    assert_eq!(body["stale"], false);
+    assert_eq!(body["freshness"]["ranked_stats"]["stale"], false);
+    assert_eq!(body["freshness"]["level"]["cached_at"], body["cached_at"]);
+    assert_eq!(body["freshness"]["champion_mastery"]["stale"], false);
+    assert!(body["freshness"]["champion_mastery"]["age_secs"].is_i64());
+}
+
+#[tokio::test]
+async fn repeated_lookup_is_served_from_cache() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (first_status, _, first) = get(&state, "/league?name=Faker&tagline=KR1").await;
+    let upstream_calls = mock.requests().len();
+    let (second_status, _, second) = get(&state, "/league?name=Faker&tagline=KR1").await;
+
+    assert_eq!(first_status, StatusCode::OK);
+    assert_eq!(second_status, StatusCode::OK);
+    assert_eq!(first, second);
+    assert_eq!(upstream_calls, 3, "account, league and summoner lookups");
+    assert_eq!(
+        mock.requests().len(),
+        upstream_calls,
+        "second lookup must not reach Riot"
+    );
+}
+
+#[tokio::test]
+async fn concurrent_lookups_share_upstream_requests() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = "/league?name=Faker&tagline=KR1";
+    let (first, second, third) = tokio::join!(get(&state, uri), get(&state, uri), get(&state, uri));
+
+    for (status, _, _) in [&first, &second, &third] {
+        assert_eq!(*status, StatusCode::OK);
+    }
+    assert_eq!(first.2, second.2);
+    assert_eq!(first.2, third.2);
+    assert_eq!(mock.requests().len(), 3, "account, league and summoner lookups");
+}
+
+#[tokio::test]
+async fn concurrent_lookups_of_unknown_riot_id_share_one_request() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = format!("/league?name={}&tagline=EUW", MISSING_NAME);
+    let (first, second, third) = tokio::join!(get(&state, &uri), get(&state, &uri), get(&state, &uri));
+
+    for (status, _, body) in [first, second, third] {
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "account_not_found");
+    }
+    assert_eq!(mock.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn lookups_use_platform_and_regional_routing() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1&platform=kr").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=VN2&platform=vn2").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let requests = mock.requests();
+    assert!(requests.contains(&"/asia/riot/account/v1/accounts/by-riot-id/Faker/KR1".to_string()));
+    assert!(requests.contains(&"/kr/lol/league/v4/entries/by-puuid/puuid-faker".to_string()));
+    assert!(requests.contains(&"/kr/lol/summoner/v4/summoners/by-puuid/puuid-faker".to_string()));
+    // SEA has no account-v1 cluster, so the account lookup goes to asia
+    assert!(requests.contains(&"/asia/riot/account/v1/accounts/by-riot-id/Faker/VN2".to_string()));
+    assert!(requests.contains(&"/vn2/lol/league/v4/entries/by-puuid/puuid-faker".to_string()));
+}
+
+#[tokio::test]
+async fn configured_default_platform_is_used_without_platform_parameter() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.default_platform = "na1".parse().unwrap();
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=NA1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let requests = mock.requests();
+    assert!(requests.contains(&"/americas/riot/account/v1/accounts/by-riot-id/Faker/NA1".to_string()));
+    assert!(requests.contains(&"/na1/lol/league/v4/entries/by-puuid/puuid-faker".to_string()));
+}
+
+#[tokio::test]
+async fn riot_ids_are_case_insensitive_and_canonicalized() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, first) = get(&state, "/league?name=faker&tagline=kr1").await;
+    let (_, _, second) = get(&state, "/league?name=FAKER&tagline=Kr1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first["riot_id"], "Faker#KR1");
+    assert_eq!(second["riot_id"], "Faker#KR1");
+    let account_requests = mock
+        .requests()
+        .iter()
+        .filter(|path| path.contains("/riot/account/"))
+        .count();
+    assert_eq!(account_requests, 1);
+}
+
+#[tokio::test]
+async fn riot_id_is_url_encoded() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/league?name=Hide%20on%20bush&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["riot_id"], "Hide on bush#KR1");
+    assert!(mock
+        .requests()
+        .contains(&"/europe/riot/account/v1/accounts/by-riot-id/Hide%20on%20bush/KR1".to_string()));
+}
+
+#[tokio::test]
+async fn renamed_account_is_detected() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.riot_id = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    let uri = format!("/league?name={}&tagline=EUW", RENAMED_NAME);
+
+    let (status, _, body) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["riot_id"], format!("{}#EUW", RENAMED_TO));
+
+    // The cached PUUID no longer belongs to the old name, which is resolved again
+    let (status, _, body) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "account_not_found");
+}
+
+#[tokio::test]
+async fn expired_player_data_is_served_when_riot_fails() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.player_data = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    let uri = format!("/league?name={}&tagline=EUW", UNSTABLE_NAME);
+
+    let (status, _, first) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first["stale"], false);
+
+    let (status, _, second) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["stale"], true);
+    assert_eq!(second["cached_at"], first["cached_at"]);
+    assert_eq!(second["ranked_stats"], first["ranked_stats"]);
+    assert_eq!(second["freshness"]["ranked_stats"]["stale"], true);
+    assert_eq!(second["freshness"]["level"]["stale"], true);
+    assert_eq!(second["freshness"]["champion_mastery"], serde_json::Value::Null);
+}
+
+#[tokio::test]
+async fn riot_failure_without_cached_data_is_an_error() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.player_data = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    let uri = format!("/league?name={}&tagline=EUW", UNSTABLE_NAME);
+
+    get(&state, &uri).await;
+    // A different platform has no cached data to fall back to
+    let (status, _, body) = get(&state, &format!("{}&platform=na1", uri)).await;
+
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["code"], "upstream_unavailable");
+}
+
+#[tokio::test]
+async fn stale_while_revalidate_refreshes_in_background() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.player_data = Duration::ZERO;
+    config.stale_while_revalidate = true;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    let league_requests = || {
+        mock.requests()
+            .iter()
+            .filter(|path| path.contains("/lol/league/"))
+            .count()
+    };
+
+    get(&state, "/league?name=Faker&tagline=KR1").await;
+    let (status, _, body) = get(&state, "/league?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["stale"], true);
+    for _ in 0..50 {
+        if league_requests() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(league_requests(), 2);
+}
+
+#[tokio::test]
+async fn unknown_riot_id_returns_account_not_found() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, &format!("/league?name={}&tagline=EUW", MISSING_NAME)).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "account_not_found");
+}
+
+#[tokio::test]
+async fn unknown_riot_id_is_cached_negatively() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+    let uri = format!("/league?name={}&tagline=EUW", MISSING_NAME);
+
+    let (_, _, first) = get(&state, &uri).await;
+    let (status, _, second) = get(&state, &uri.to_lowercase()).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(second["code"], "account_not_found");
+    assert!(first.get("cached_at").is_none());
+    assert!(second["cached_at"].is_string());
+    let account_lookups = mock
+        .requests()
+        .iter()
+        .filter(|path| path.contains("/accounts/by-riot-id/"))
+        .count();
+    assert_eq!(account_lookups, 1);
+}
+
+#[tokio::test]
+async fn unknown_riot_id_is_looked_up_again_after_negative_ttl() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.negative = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    let uri = format!("/league?name={}&tagline=EUW", MISSING_NAME);
+
+    get(&state, &uri).await;
+    let (_, _, body) = get(&state, &uri).await;
+
+    assert!(body.get("cached_at").is_none());
+    assert_eq!(mock.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn transient_account_errors_are_not_cached() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.retry.max_retries = 0;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    let uri = format!("/league?name={}&tagline=EUW", FLAKY_NAME);
+
+    let (first, _, _) = get(&state, &uri).await;
+    let (second, _, _) = get(&state, &uri).await;
+
+    assert_eq!(first, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(second, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn missing_summoner_on_platform_returns_account_not_found() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, &format!("/league?name={}&tagline=EUW", NEWBIE_NAME)).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "account_not_found");
+}
+
+#[tokio::test]
+async fn unplayed_champion_reports_zero_mastery() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = format!("/league?name=Faker&tagline=KR1&champion={}", UNPLAYED_CHAMPION);
+    let (status, _, body) = get(&state, &uri).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["champion_mastery"],
+        json!({ "championLevel": 0, "championPoints": 0 })
+    );
+}
+
+#[tokio::test]
+async fn empty_results_are_marked_negative() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = format!("/v2/league?name=Faker&tagline=KR1&champion={}", UNPLAYED_CHAMPION);
+    let (status, _, body) = get(&state, &uri).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["freshness"]["champion_mastery"]["negative"], true);
+    assert_eq!(body["freshness"]["ranked_stats"]["negative"], false);
+}
+
+#[tokio::test]
+async fn unplayed_champion_is_refetched_after_negative_ttl() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.negative = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+
+    get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+    let uri = format!("/league?name=Faker&tagline=KR1&champion={}", UNPLAYED_CHAMPION);
+    get(&state, &uri).await;
+    get(&state, &uri).await;
+    get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+
+    let mastery_lookups = |champion: &str| {
+        mock.requests()
+            .iter()
+            .filter(|path| path.ends_with(&format!("/by-champion/{}", champion)))
+            .count()
+    };
+    assert_eq!(mastery_lookups(UNPLAYED_CHAMPION), 2);
+    assert_eq!(mastery_lookups("157"), 1);
+}
+
+#[tokio::test]
+async fn revoked_key_returns_key_invalid() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(REVOKED_KEY);
+
+    let (status, _, body) = get(&state, "/league?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["code"], "key_invalid");
+}
+
+#[tokio::test]
+async fn exhausted_rate_limit_returns_503_with_retry_after() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.retry.max_retries = 0;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+
+    let (status, headers, body) = get(&state, &format!("/league?name={}&tagline=EUW", THROTTLED_NAME)).await;
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["code"], "rate_limited");
+    assert_eq!(headers[header::RETRY_AFTER], "1");
+    assert_eq!(mock.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn huge_queue_wait_does_not_overflow() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.retry.max_queue_wait = Duration::MAX;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+
+    let (status, _, body) = get(&state, "/league?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["level"], 512);
+}
+
+#[tokio::test]
+async fn rate_limited_request_is_retried_after_retry_after() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, &format!("/league?name={}&tagline=EUW", FLAKY_NAME)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["level"], 512);
+    let account_requests = mock
+        .requests()
+        .iter()
+        .filter(|path| path.contains("/riot/account/"))
+        .count();
+    assert_eq!(account_requests, 2);
+}
+
+#[tokio::test]
+async fn metrics_count_requests_cache_lookups_and_upstream_calls() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    get(&state, "/league?name=Faker&tagline=KR1").await;
+    get(&state, "/league?name=Faker&tagline=KR1").await;
+
+    let metrics = get_text(&state, "/metrics").await;
+
+    assert!(metrics.contains(r#"sedidata_http_requests_total{route="/league",status="200"} 2"#));
+    assert!(metrics.contains(r#"sedidata_cache_lookups_total{map="puuid",result="hit"} 1"#));
+    assert!(metrics.contains(r#"sedidata_cache_lookups_total{map="puuid",result="miss"} 1"#));
+    assert!(metrics.contains(r#"sedidata_upstream_responses_total{method="account-v1.getByRiotId",status="200"} 1"#));
+    assert!(metrics.contains(r#"sedidata_cache_entries{map="puuid"} 1"#));
+}
+
+#[tokio::test]
+async fn batch_keeps_order_and_reports_errors_per_player() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let players = json!([
+        { "name": MISSING_NAME, "tagline": "EUW" },
+        { "name": "Faker", "tagline": "KR1", "champion": "157" },
+        { "name": "", "tagline": "EUW" },
+    ]);
+    let (status, _, body) = post_json(&state, "/league/batch", players).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let results = body.as_array().unwrap();
+    assert_eq!(results.len(), 3);
+
+    assert_eq!(results[0]["name"], MISSING_NAME);
+    assert_eq!(results[0]["data"], serde_json::Value::Null);
+    assert_eq!(results[0]["error"]["code"], "account_not_found");
+    assert_eq!(results[0]["error"]["status"], 404);
+
+    assert_eq!(results[1]["name"], "Faker");
+    assert_eq!(results[1]["data"]["level"], 512);
+    assert_eq!(results[1]["data"]["champion_mastery"]["championLevel"], 42);
+
+    assert_eq!(results[2]["error"]["code"], "invalid_request");
+}
+
+#[tokio::test]
+async fn oversized_batch_is_rejected() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let players: Vec<_> = (0..21)
+        .map(|i| json!({ "name": format!("Player{}", i), "tagline": "EUW" }))
+        .collect();
+    let (status, _, body) = post_json(&state, "/league/batch", json!(players)).await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "invalid_request");
+    assert!(mock.requests().is_empty());
+}
+
+#[tokio::test]
+async fn empty_name_is_rejected_without_upstream_call() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/league?name=&tagline=EUW").await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["code"], "invalid_request");
+    assert!(mock.requests().is_empty());
+}
+
+#[tokio::test]
+async fn responses_carry_request_id() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let request = Request::get("/heartbeat")
+        .header("X-Request-Id", "test-request")
+        .body(Body::empty())
+        .unwrap();
+    let (status, headers, _) = send(&state, request).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers["x-request-id"], "test-request");
+}
+
+#[tokio::test]
+async fn active_game_lists_participants_and_bans() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = format!("/active-game?name={}&tagline=KR1&platform=kr", IN_GAME_NAME);
+    let (status, _, body) = get(&state, &uri).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["game_id"], 7412893561u64);
+    assert_eq!(body["queue_id"], 420);
+    assert_eq!(body["game_length_seconds"], 842);
+    assert_eq!(body["participants"].as_array().unwrap().len(), 2);
+    assert_eq!(body["participants"][0]["riot_id"], "Faker#KR1");
+    assert_eq!(body["participants"][0]["champion_id"], 157);
+    assert_eq!(body["participants"][0]["is_requested_player"], true);
+    assert_eq!(body["participants"][1]["is_requested_player"], false);
+    assert_eq!(body["bans"][0]["champion_id"], 266);
+    assert_eq!(body["bans"][1]["champion_id"], -1);
+    assert!(mock
+        .requests()
+        .contains(&"/kr/lol/spectator/v5/active-games/by-summoner/puuid-faker".to_string()));
+}
+
+#[tokio::test]
+async fn player_not_in_game_returns_not_found() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/active-game?name=Chovy&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["error"], "No active game found");
+}
+
+#[tokio::test]
+async fn league_history_records_only_changed_snapshots() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.player_data = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+
+    for _ in 0..3 {
+        let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _, body) = get(&state, "/league/history?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let queues = body["queues"].as_object().unwrap();
+    assert_eq!(queues.len(), 2);
+    assert_eq!(queues["RANKED_SOLO_5x5"].as_array().unwrap().len(), 1);
+    assert_eq!(queues["RANKED_SOLO_5x5"][0]["tier"], "CHALLENGER");
+    assert_eq!(queues["RANKED_SOLO_5x5"][0]["league_points"], 1204);
+}
+
+#[tokio::test]
+async fn league_history_tracks_lp_changes_per_queue() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.player_data = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+
+    let uri = format!("/league?name={}&tagline=EUW", CLIMBER_NAME);
+    get(&state, &uri).await;
+    get(&state, &uri).await;
+    let uri = format!(
+        "/league/history?name={}&tagline=EUW&queue=RANKED_SOLO_5x5",
+        CLIMBER_NAME
+    );
+    let (status, _, body) = get(&state, &uri).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let queues = body["queues"].as_object().unwrap();
+    assert_eq!(queues.len(), 1);
+    let points: Vec<_> = queues["RANKED_SOLO_5x5"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|snapshot| snapshot["league_points"].as_i64().unwrap())
+        .collect();
+    assert_eq!(points, [1010, 1020, 1030]);
+}
+
+#[tokio::test]
+async fn cached_data_survives_shutdown_and_restart() {
+    let mock = MockRiot::start().await;
+    let path = std::env::temp_dir().join(format!("sedidata-shutdown-{}.db", std::process::id()));
+    let mut config = mock.config();
+    config.cache_path = path.to_string_lossy().into_owned();
+
+    let state = AppState::new(config.clone(), vec![KEY.to_string()]).unwrap();
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1").await;
+    assert_eq!(status, StatusCode::OK);
+    state.shutdown().await;
+    drop(state);
+    let upstream_calls = mock.requests().len();
+
+    let restarted = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    let (status, _, body) = get(&restarted, "/league?name=Faker&tagline=KR1").await;
+    let (_, _, history) = get(&restarted, "/league/history?name=Faker&tagline=KR1").await;
+    drop(restarted);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+    }
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["level"], 512);
+    assert_eq!(
+        mock.requests().len(),
+        upstream_calls,
+        "restarted server must use the flushed cache"
+    );
+    assert_eq!(history["queues"].as_object().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn v2_league_returns_typed_response() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, headers, body) = get(&state, "/v2/league?name=faker&tagline=kr1&champion=157").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+    let league: LeagueResponse = serde_json::from_value(body).unwrap();
+    assert_eq!(league.riot_id.to_string(), "Faker#KR1");
+    assert_eq!(league.level, 512);
+    assert_eq!(league.ranked_stats[0].queue_type, "RANKED_SOLO_5x5");
+    assert_eq!(league.ranked_stats[0].league_points, 1204);
+    let mastery = league.champion_mastery.unwrap();
+    assert_eq!((mastery.level, mastery.points), (42, 487310));
+    assert!(!league.freshness.ranked_stats.stale);
+}
+
+#[tokio::test]
+async fn v2_batch_reports_errors_per_player() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let players = json!([
+        { "name": "Faker", "tagline": "KR1" },
+        { "name": MISSING_NAME, "tagline": "EUW" },
+    ]);
+    let (status, _, body) = post_json(&state, "/v2/league/batch", players).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let entries: Vec<LeagueBatchEntry> = serde_json::from_value(body).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].data.as_ref().unwrap().level, 512);
+    assert!(entries[0].error.is_none());
+    assert!(entries[1].data.is_none());
+    assert_eq!(entries[1].error.as_ref().unwrap().code, "account_not_found");
+}
+
+#[tokio::test]
+async fn openapi_document_describes_v2_endpoints() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/openapi.json").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["openapi"], "3.0.3");
+    assert!(body["paths"]["/v2/league"]["get"].is_object());
+    assert!(body["paths"]["/v2/league/batch"]["post"].is_object());
+    let schemas = &body["components"]["schemas"];
+    assert!(schemas["LeagueResponse"]["properties"]["ranked_stats"].is_object());
+    assert!(schemas["ErrorResponse"]["properties"]["code"].is_object());
+    assert!(schemas["Platform"]["enum"].as_array().unwrap().contains(&json!("euw1")));
+    assert!(mock.requests().is_empty());
+}
+
+#[tokio::test]
+async fn ready_reports_valid_key() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/ready").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["ready"], true);
+    assert_eq!(body["riot_api_key"], "valid");
+    assert_eq!(body["upstream"], "reachable");
+    assert_eq!(body["cache"], "ok");
+    assert!(body["checked_at"].is_string());
+}
+
+#[tokio::test]
+async fn ready_reports_expired_key() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(REVOKED_KEY);
+
+    let (status, _, body) = get(&state, "/ready").await;
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["ready"], false);
+    assert_eq!(body["riot_api_key"], "invalid");
+    assert_eq!(body["upstream"], "reachable");
+}
+
+#[tokio::test]
+async fn ready_reports_unreachable_upstream() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    // Nothing listens on the discard port
+    config.riot_api_base_url = "http://127.0.0.1:9/{routing}".to_string();
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+
+    let (status, _, body) = get(&state, "/ready").await;
+
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["riot_api_key"], "unknown");
+    assert_eq!(body["upstream"], "unreachable");
+}
+
+#[tokio::test]
+async fn ready_checks_key_only_once_until_next_interval() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    get(&state, "/ready").await;
+    get(&state, "/ready").await;
+
+    let checks = mock
+        .requests()
+        .iter()
+        .filter(|path| path.contains("/platform-data"))
+        .count();
+    assert_eq!(checks, 1);
+}
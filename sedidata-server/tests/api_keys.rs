@@ -0,0 +1,176 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::*;
+use sedidata_server::{config::Config, load_keys, AppState};
+
+const KEY: &str = "test-key";
+const SECOND_KEY: &str = "second-key";
+
+fn keys(keys: &[&str]) -> Vec<String> {
+    keys.iter().map(|key| key.to_string()).collect()
+}
+
+/// Unique key file in the temp directory, removed when dropped
+struct TempKeyFile(std::path::PathBuf);
+
+impl TempKeyFile {
+    fn new(name: &str, contents: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("sedidata-{}-{}.keys", name, std::process::id()));
+        let file = Self(path);
+        file.write(contents);
+        file
+    }
+
+    fn write(&self, contents: &str) {
+        std::fs::write(&self.0, contents).unwrap();
+    }
+}
+
+impl Drop for TempKeyFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[tokio::test]
+async fn requests_are_spread_across_keys() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(mock.config(), keys(&[KEY, SECOND_KEY])).unwrap();
+
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let used = mock.keys();
+    assert!(used.iter().any(|key| key == KEY));
+    assert!(used.iter().any(|key| key == SECOND_KEY));
+}
+
+#[tokio::test]
+async fn rejected_key_is_taken_out_of_rotation() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(mock.config(), keys(&[REVOKED_KEY, KEY])).unwrap();
+
+    get(&state, "/league?name=Faker&tagline=KR1").await;
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+
+    assert_eq!(status, StatusCode::OK);
+    // The first lookup and the key check confirming the rejection
+    let revoked_uses = mock.keys().iter().filter(|key| *key == REVOKED_KEY).count();
+    assert!(revoked_uses <= 2);
+
+    let metrics = get_text(&state, "/metrics").await;
+    assert!(metrics.contains(r#"sedidata_riot_api_keys{state="active"} 1"#));
+}
+
+#[tokio::test]
+async fn ready_counts_active_keys() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(mock.config(), keys(&[REVOKED_KEY, KEY])).unwrap();
+
+    let (status, _, body) = get(&state, "/ready").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["riot_api_key"], "valid");
+    assert_eq!(body["active_keys"], 1);
+    assert_eq!(body["total_keys"], 2);
+}
+
+#[tokio::test]
+async fn exhausted_key_does_not_block_other_keys() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(mock.config(), keys(&[EXHAUSTED_KEY, KEY])).unwrap();
+
+    let (status, _, body) = get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["level"], 512);
+    // Its application limit is blocked for a minute after the first 429
+    let exhausted_uses = mock.keys().iter().filter(|key| *key == EXHAUSTED_KEY).count();
+    assert!(exhausted_uses <= 1);
+}
+
+#[tokio::test]
+async fn lookups_fail_without_asking_riot_once_every_key_was_rejected() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(mock.config(), keys(&[REVOKED_KEY])).unwrap();
+
+    get(&state, "/league?name=Faker&tagline=KR1").await;
+    let (status, _, body) = get(&state, "/league?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["code"], "key_invalid");
+    // The first lookup and the key check confirming the rejection
+    assert_eq!(mock.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn non_numeric_champion_is_rejected_without_upstream_call() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(mock.config(), keys(&[KEY, SECOND_KEY])).unwrap();
+
+    for champion in ["1%2F..%2F..%2Fx", "yasuo", "-1"] {
+        let uri = format!("/league?name=Faker&tagline=KR1&champion={}", champion);
+        let (status, _, body) = get(&state, &uri).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "invalid_request");
+    }
+
+    assert!(mock.requests().is_empty());
+    let metrics = get_text(&state, "/metrics").await;
+    assert!(metrics.contains(r#"sedidata_riot_api_keys{state="active"} 2"#));
+}
+
+#[tokio::test]
+async fn forbidden_endpoint_does_not_reject_valid_keys() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(mock.config(), keys(&[KEY, SECOND_KEY])).unwrap();
+
+    let uri = format!("/league?name=Faker&tagline=KR1&champion={}", FORBIDDEN_CHAMPION);
+    let (status, _, _) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+
+    // The key check vouched for the key, so it stays in rotation along with the other one
+    let metrics = get_text(&state, "/metrics").await;
+    assert!(metrics.contains(r#"sedidata_riot_api_keys{state="active"} 2"#));
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1&champion=157").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn keys_are_reloaded_from_key_file() {
+    let mock = MockRiot::start().await;
+    let file = TempKeyFile::new("reload", &format!("# expired\n{}\n", REVOKED_KEY));
+    let config = Config {
+        riot_api_keys_file: Some(file.0.clone()),
+        ..mock.config()
+    };
+    let state = AppState::new(config.clone(), load_keys(&config).unwrap()).unwrap();
+
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1").await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+
+    file.write(&format!("{}\n\n{}\n", KEY, SECOND_KEY));
+    let changes = state.reload_api_keys().unwrap();
+    assert_eq!((changes.added, changes.removed), (2, 1));
+
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn unreadable_key_file_keeps_current_keys() {
+    let mock = MockRiot::start().await;
+    let file = TempKeyFile::new("empty", &format!("{}\n", KEY));
+    let config = Config {
+        riot_api_keys_file: Some(file.0.clone()),
+        ..mock.config()
+    };
+    let state = AppState::new(config.clone(), load_keys(&config).unwrap()).unwrap();
+
+    file.write("# all keys removed by accident\n");
+    assert!(state.reload_api_keys().is_err());
+
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1").await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+};
+use common::*;
+use sedidata_server::{
+    config::{ClientToken, Config},
+    AppState,
+};
+use serde_json::{json, Value};
+
+const KEY: &str = "test-key";
+const TUI_TOKEN: &str = "tui-secret";
+const LIMITED_TOKEN: &str = "limited-secret";
+
+fn with_clients(config: Config) -> Config {
+    Config {
+        client_tokens: vec![
+            ClientToken {
+                name: "tui".to_string(),
+                token: TUI_TOKEN.to_string(),
+                requests_per_minute: None,
+            },
+            ClientToken {
+                name: "limited".to_string(),
+                token: LIMITED_TOKEN.to_string(),
+                requests_per_minute: Some(2),
+            },
+        ],
+        ..config
+    }
+}
+
+fn authorized_post(uri: &str, token: &str, body: Value) -> Request<Body> {
+    Request::post(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn authorized(uri: &str, token: &str) -> Request<Body> {
+    Request::get(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn requests_are_anonymous_without_configured_tokens() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, _) = get(&state, "/league?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn missing_token_is_rejected_without_upstream_call() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_clients(mock.config()), vec![KEY.to_string()]).unwrap();
+
+    let (status, headers, body) = get(&state, "/league?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "unauthorized");
+    assert_eq!(headers[header::WWW_AUTHENTICATE], "Bearer");
+    assert!(mock.requests().is_empty());
+}
+
+#[tokio::test]
+async fn unknown_token_is_rejected() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_clients(mock.config()), vec![KEY.to_string()]).unwrap();
+
+    let (status, _, body) = send(&state, authorized("/league?name=Faker&tagline=KR1", "guessed")).await;
+
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "unauthorized");
+}
+
+#[tokio::test]
+async fn valid_token_is_accepted() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_clients(mock.config()), vec![KEY.to_string()]).unwrap();
+
+    let (status, _, body) = send(&state, authorized("/league?name=Faker&tagline=KR1", TUI_TOKEN)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["level"], 512);
+}
+
+#[tokio::test]
+async fn heartbeat_and_metrics_stay_public() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_clients(mock.config()), vec![KEY.to_string()]).unwrap();
+
+    let (heartbeat_status, _, _) = get(&state, "/heartbeat").await;
+    let metrics = get_text(&state, "/metrics").await;
+
+    assert_eq!(heartbeat_status, StatusCode::OK);
+    assert!(metrics.contains("sedidata_http_requests_total"));
+}
+
+#[tokio::test]
+async fn quota_is_enforced_per_client() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_clients(mock.config()), vec![KEY.to_string()]).unwrap();
+    let uri = "/league?name=Faker&tagline=KR1";
+
+    for _ in 0..2 {
+        let (status, _, _) = send(&state, authorized(uri, LIMITED_TOKEN)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, headers, body) = send(&state, authorized(uri, LIMITED_TOKEN)).await;
+
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "quota_exceeded");
+    assert!(headers.contains_key(header::RETRY_AFTER));
+
+    // Other clients have their own quota
+    let (status, _, _) = send(&state, authorized(uri, TUI_TOKEN)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn batch_counts_every_player_against_quota() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_clients(mock.config()), vec![KEY.to_string()]).unwrap();
+    let players = json!([
+        { "name": "Faker", "tagline": "KR1" },
+        { "name": "Faker", "tagline": "KR1" },
+    ]);
+
+    let (status, _, _) = send(&state, authorized_post("/v2/league/batch", LIMITED_TOKEN, players)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Both requests of the minute were used by the two players
+    let (status, _, body) = send(&state, authorized("/league?name=Faker&tagline=KR1", LIMITED_TOKEN)).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "quota_exceeded");
+}
+
+#[tokio::test]
+async fn batch_larger_than_quota_is_rejected_without_upstream_call() {
+    let mock = MockRiot::start().await;
+    let state = AppState::new(with_clients(mock.config()), vec![KEY.to_string()]).unwrap();
+    let players = json!([
+        { "name": "Faker", "tagline": "KR1" },
+        { "name": "Faker", "tagline": "KR1" },
+        { "name": "Faker", "tagline": "KR1" },
+    ]);
+
+    let (status, _, body) = send(&state, authorized_post("/league/batch", LIMITED_TOKEN, players)).await;
+
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body["code"], "quota_exceeded");
+    assert!(mock.requests().is_empty());
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use common::*;
+use sedidata_common::{ChallengePercentilesResponse, ChallengesBatchEntry, ChallengesResponse};
+use sedidata_server::AppState;
+use serde_json::json;
+
+const KEY: &str = "test-key";
+
+fn count(requests: &[String], path: &str) -> usize {
+    requests.iter().filter(|request| request.contains(path)).count()
+}
+
+#[tokio::test]
+async fn challenges_return_totals_categories_and_progress() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/v2/challenges?name=faker&tagline=kr1&platform=kr").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let challenges: ChallengesResponse = serde_json::from_value(body).unwrap();
+    assert_eq!(challenges.riot_id.to_string(), "Faker#KR1");
+    assert_eq!(challenges.total_points.level, "MASTER");
+    assert_eq!(
+        (challenges.total_points.current, challenges.total_points.max),
+        (14820, 38870)
+    );
+    assert_eq!(challenges.category_points["TEAMWORK"].current, 2150);
+    assert_eq!(challenges.challenges.len(), 3);
+    assert_eq!(challenges.challenges[1].challenge_id, 101000);
+    assert_eq!(challenges.challenges[1].percentile, Some(0.034));
+    assert_eq!(
+        challenges.challenges[0].achieved_at.unwrap().timestamp_millis(),
+        1718123456789
+    );
+    assert!(challenges.challenges[2].achieved_at.is_none());
+    assert!(!challenges.freshness.negative);
+    assert!(mock
+        .requests()
+        .contains(&"/kr/lol/challenges/v1/player-data/puuid-faker".to_string()));
+}
+
+#[tokio::test]
+async fn player_challenges_are_cached() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    get(&state, "/v2/challenges?name=Faker&tagline=KR1").await;
+    let (status, _, _) = get(&state, "/v2/challenges?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(count(&mock.requests(), "/challenges/v1/player-data/"), 1);
+}
+
+#[tokio::test]
+async fn player_without_challenges_is_marked_negative() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = format!("/v2/challenges?name={}&tagline=EUW", NEWBIE_NAME);
+    let (status, _, body) = get(&state, &uri).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["challenges"], json!([]));
+    assert_eq!(body["freshness"]["negative"], true);
+}
+
+#[tokio::test]
+async fn expired_challenges_are_served_when_riot_fails() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.player_challenges = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    let uri = format!("/v2/challenges?name={}&tagline=EUW", UNSTABLE_NAME);
+
+    let (status, _, first) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first["freshness"]["stale"], false);
+
+    let (status, _, second) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["freshness"]["stale"], true);
+    assert_eq!(second["total_points"], first["total_points"]);
+}
+
+#[tokio::test]
+async fn challenges_batch_reports_errors_per_player() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let players = json!([
+        { "name": "Faker", "tagline": "KR1" },
+        { "name": MISSING_NAME, "tagline": "EUW" },
+    ]);
+    let (status, _, body) = post_json(&state, "/v2/challenges/batch", players).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let entries: Vec<ChallengesBatchEntry> = serde_json::from_value(body).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].data.as_ref().unwrap().total_points.current, 14820);
+    assert!(entries[1].data.is_none());
+    assert_eq!(entries[1].error.as_ref().unwrap().code, "account_not_found");
+}
+
+#[tokio::test]
+async fn percentiles_are_cached_per_platform() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/v2/challenges/percentiles?platform=kr").await;
+    get(&state, "/v2/challenges/percentiles?platform=kr").await;
+    get(&state, "/v2/challenges/percentiles?platform=euw1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let percentiles: ChallengePercentilesResponse = serde_json::from_value(body).unwrap();
+    assert_eq!(percentiles.percentiles[&0]["MASTER"], 0.012);
+    assert_eq!(percentiles.percentiles[&101101]["GOLD"], 0.21);
+    let requests = mock.requests();
+    assert_eq!(count(&requests, "/kr/lol/challenges/v1/challenges/percentiles"), 1);
+    assert_eq!(count(&requests, "/euw1/lol/challenges/v1/challenges/percentiles"), 1);
+}
+
+#[tokio::test]
+async fn concurrent_percentile_lookups_share_one_request() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = "/v2/challenges/percentiles";
+    let (first, second, third) = tokio::join!(get(&state, uri), get(&state, uri), get(&state, uri));
+
+    for (status, _, _) in [first, second, third] {
+        assert_eq!(status, StatusCode::OK);
+    }
+    assert_eq!(count(&mock.requests(), "/challenges/percentiles"), 1);
+}
+
+#[tokio::test]
+async fn openapi_document_describes_challenge_endpoints() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (_, _, body) = get(&state, "/openapi.json").await;
+
+    assert!(body["paths"]["/v2/challenges"]["get"].is_object());
+    assert!(body["paths"]["/v2/challenges/batch"]["post"].is_object());
+    assert!(body["paths"]["/v2/challenges/percentiles"]["get"].is_object());
+    let schemas = &body["components"]["schemas"];
+    assert!(schemas["ChallengesResponse"]["properties"]["total_points"].is_object());
+    assert!(schemas["ChallengePercentilesResponse"]["properties"]["percentiles"].is_object());
+}
@@ -0,0 +1,425 @@
+//! In-process mock of the Riot API serving fixture JSON, plus helpers to drive the real router against it
+#![allow(dead_code)] // Each test binary uses a different subset of the helpers
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing, Router,
+};
+use sedidata_server::{
+    config::{Config, RetryPolicy},
+    router, AppState,
+};
+use serde_json::Value;
+use tower::ServiceExt;
+
+const LEAGUE_ENTRIES: &str = include_str!("../fixtures/league_entries.json");
+const SUMMONER: &str = include_str!("../fixtures/summoner.json");
+const CHAMPION_MASTERY: &str = include_str!("../fixtures/champion_mastery.json");
+const ACTIVE_GAME: &str = include_str!("../fixtures/active_game.json");
+const PLAYER_CHALLENGES: &str = include_str!("../fixtures/player_challenges.json");
+const CHALLENGE_PERCENTILES: &str = include_str!("../fixtures/challenge_percentiles.json");
+const CHAMPION_MASTERIES: &str = include_str!("../fixtures/champion_masteries.json");
+const MATCH_IDS: &str = include_str!("../fixtures/match_ids.json");
+const MATCH: &str = include_str!("../fixtures/match.json");
+
+/// API key the mock answers with 403, like an expired development key
+pub const REVOKED_KEY: &str = "revoked-key";
+/// API key whose application rate limit is exhausted, the mock answers with 429
+pub const EXHAUSTED_KEY: &str = "exhausted-key";
+/// Riot ID that does not exist
+pub const MISSING_NAME: &str = "Missing";
+/// Riot ID whose account lookup always answers 429
+pub const THROTTLED_NAME: &str = "Throttled";
+/// Riot ID whose account lookup answers 429 once and succeeds afterwards
+pub const FLAKY_NAME: &str = "Flaky";
+/// Riot ID with an account but no summoner on the requested platform
+pub const NEWBIE_NAME: &str = "Newbie";
+/// Riot ID currently playing the fixture game, everyone else is not in game
+pub const IN_GAME_NAME: &str = "Faker";
+/// Riot ID whose account is renamed to `RENAMED_TO`, afterwards the old name no longer exists
+pub const RENAMED_NAME: &str = "Oldname";
+pub const RENAMED_TO: &str = "Newname";
+/// Riot ID that gains LP on every league lookup
+pub const CLIMBER_NAME: &str = "Climber";
+/// Riot ID whose league, challenge, mastery list and match list lookups fail with 503 after the first one
+pub const UNSTABLE_NAME: &str = "Unstable";
+/// Champion id without any mastery
+pub const UNPLAYED_CHAMPION: &str = "0";
+/// Champion id the mock answers with 403 for every key, like a route the key has no access to
+pub const FORBIDDEN_CHAMPION: &str = "403";
+/// Mastery score the mock reports for every player with masteries
+pub const MASTERY_SCORE: u64 = 85;
+
+#[derive(Clone, Default)]
+struct MockState {
+    requests: Arc<Mutex<Vec<String>>>,
+    /// `X-Riot-Token` of every request, in the same order as `requests`
+    keys: Arc<Mutex<Vec<String>>>,
+    attempts: Arc<Mutex<HashMap<String, usize>>>,
+    /// Riot ID of every account handed out, by PUUID
+    accounts: Arc<Mutex<HashMap<String, (String, String)>>>,
+}
+
+pub struct MockRiot {
+    pub base_url: String,
+    state: MockState,
+}
+
+impl MockRiot {
+    /// Starts the mock on a random local port, routing values become the first path segment
+    pub async fn start() -> Self {
+        let state = MockState::default();
+        let app = Router::new()
+            .route(
+                "/:routing/riot/account/v1/accounts/by-riot-id/:name/:tagline",
+                routing::get(account_by_riot_id),
+            )
+            .route(
+                "/:routing/riot/account/v1/accounts/by-puuid/:puuid",
+                routing::get(account_by_puuid),
+            )
+            .route(
+                "/:routing/lol/league/v4/entries/by-puuid/:puuid",
+                routing::get(league_entries),
+            )
+            .route(
+                "/:routing/lol/summoner/v4/summoners/by-puuid/:puuid",
+                routing::get(summoner),
+            )
+            .route(
+                "/:routing/lol/champion-mastery/v4/champion-masteries/by-puuid/:puuid/by-champion/:champion",
+                routing::get(champion_mastery),
+            )
+            .route(
+                "/:routing/lol/champion-mastery/v4/champion-masteries/by-puuid/:puuid",
+                routing::get(champion_masteries),
+            )
+            .route(
+                "/:routing/lol/champion-mastery/v4/scores/by-puuid/:puuid",
+                routing::get(mastery_score),
+            )
+            .route(
+                "/:routing/lol/match/v5/matches/by-puuid/:puuid/ids",
+                routing::get(match_ids),
+            )
+            .route("/:routing/lol/match/v5/matches/:match_id", routing::get(riot_match))
+            .route(
+                "/:routing/lol/spectator/v5/active-games/by-summoner/:puuid",
+                routing::get(active_game),
+            )
+            .route(
+                "/:routing/lol/challenges/v1/player-data/:puuid",
+                routing::get(player_challenges),
+            )
+            .route(
+                "/:routing/lol/challenges/v1/challenges/percentiles",
+                routing::get(challenge_percentiles),
+            )
+            .route("/:routing/lol/status/v4/platform-data", routing::get(platform_status))
+            .layer(middleware::from_fn_with_state(state.clone(), check_key))
+            .with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        Self {
+            base_url: format!("http://{}/{{routing}}", addr),
+            state,
+        }
+    }
+
+    /// Paths of all requests received so far, in order, still percent-encoded
+    pub fn requests(&self) -> Vec<String> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// API keys of all requests received so far, in order
+    pub fn keys(&self) -> Vec<String> {
+        self.state.keys.lock().unwrap().clone()
+    }
+
+    /// Server configuration pointing at this mock with an in-memory cache
+    pub fn config(&self) -> Config {
+        let defaults = Config::default();
+        Config {
+            cache_path: ":memory:".to_string(),
+            riot_api_base_url: self.base_url.clone(),
+            retry: RetryPolicy {
+                max_queue_wait: Duration::from_secs(2),
+                ..defaults.retry
+            },
+            ..defaults
+        }
+    }
+
+    pub fn app_state(&self, api_key: &str) -> AppState {
+        AppState::new(self.config(), vec![api_key.to_string()]).unwrap()
+    }
+}
+
+/// Records every request and rejects the revoked key, like Riot does for expired keys
+async fn check_key(State(state): State<MockState>, request: Request, next: Next) -> Response {
+    state.requests.lock().unwrap().push(request.uri().path().to_string());
+
+    let key = request.headers().get("X-Riot-Token").and_then(|v| v.to_str().ok());
+    state.keys.lock().unwrap().push(key.unwrap_or_default().to_string());
+    match key {
+        Some(REVOKED_KEY) => forbidden(),
+        Some(EXHAUSTED_KEY) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("Retry-After", "60"), ("X-Rate-Limit-Type", "application")],
+            r#"{"status":{"message":"Rate limit exceeded","status_code":429}}"#,
+        )
+            .into_response(),
+        _ => next.run(request).await,
+    }
+}
+
+async fn account_by_riot_id(
+    State(state): State<MockState>,
+    Path((_, name, tagline)): Path<(String, String, String)>,
+) -> Response {
+    let attempt = {
+        let mut attempts = state.attempts.lock().unwrap();
+        let attempt = attempts.entry(name.clone()).or_default();
+        *attempt += 1;
+        *attempt
+    };
+
+    match name.as_str() {
+        MISSING_NAME => not_found(),
+        THROTTLED_NAME => too_many_requests(),
+        FLAKY_NAME if attempt == 1 => too_many_requests(),
+        RENAMED_NAME if attempt > 1 => not_found(),
+        _ => {
+            // Riot IDs are case-insensitive, answer with the capitalization the player chose
+            let puuid = puuid_of(&name);
+            let riot_id = (capitalize(&name), tagline.to_uppercase());
+            state.accounts.lock().unwrap().insert(puuid.clone(), riot_id.clone());
+            account(&puuid, &riot_id.0, &riot_id.1)
+        }
+    }
+}
+
+async fn account_by_puuid(State(state): State<MockState>, Path((_, puuid)): Path<(String, String)>) -> Response {
+    if puuid == puuid_of(RENAMED_NAME) {
+        return account(&puuid, RENAMED_TO, "EUW");
+    }
+    match state.accounts.lock().unwrap().get(&puuid) {
+        Some((name, tagline)) => account(&puuid, name, tagline),
+        None => not_found(),
+    }
+}
+
+/// PUUID the mock assigns to a Riot ID
+pub fn puuid_of(name: &str) -> String {
+    format!("puuid-{}", name.to_lowercase().replace(' ', ""))
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect()
+    })
+}
+
+fn account(puuid: &str, name: &str, tagline: &str) -> Response {
+    json(format!(
+        r#"{{"puuid":"{}","gameName":"{}","tagLine":"{}"}}"#,
+        puuid, name, tagline
+    ))
+}
+
+async fn league_entries(State(state): State<MockState>, Path((_, puuid)): Path<(String, String)>) -> Response {
+    if puuid == puuid_of(NEWBIE_NAME) {
+        return json("[]".to_string());
+    }
+    let lookups = {
+        let mut attempts = state.attempts.lock().unwrap();
+        let lookups = attempts.entry(puuid.clone()).or_default();
+        *lookups += 1;
+        *lookups
+    };
+    if puuid == puuid_of(UNSTABLE_NAME) && lookups > 1 {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable").into_response();
+    }
+    if puuid == puuid_of(CLIMBER_NAME) {
+        let mut entries: Value = serde_json::from_str(LEAGUE_ENTRIES).unwrap();
+        entries[0]["leaguePoints"] = Value::from(1000 + 10 * lookups);
+        return json(entries.to_string());
+    }
+    json(LEAGUE_ENTRIES.to_string())
+}
+
+async fn summoner(Path((_, puuid)): Path<(String, String)>) -> Response {
+    if puuid == puuid_of(NEWBIE_NAME) {
+        return not_found();
+    }
+    json(SUMMONER.to_string())
+}
+
+async fn platform_status() -> Response {
+    json(r#"{"id":"EUW1","name":"EU West","incidents":[],"maintenances":[]}"#.to_string())
+}
+
+async fn champion_mastery(Path((_, _, champion)): Path<(String, String, String)>) -> Response {
+    if champion == UNPLAYED_CHAMPION {
+        return not_found();
+    }
+    if champion == FORBIDDEN_CHAMPION {
+        return forbidden();
+    }
+    json(CHAMPION_MASTERY.to_string())
+}
+
+async fn champion_masteries(State(state): State<MockState>, Path((_, puuid)): Path<(String, String)>) -> Response {
+    if puuid == puuid_of(NEWBIE_NAME) {
+        return json("[]".to_string());
+    }
+    if puuid == puuid_of(UNSTABLE_NAME) && count_lookup(&state, format!("masteries:{}", puuid)) > 1 {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable").into_response();
+    }
+    json(CHAMPION_MASTERIES.to_string())
+}
+
+async fn mastery_score(Path((_, puuid)): Path<(String, String)>) -> Response {
+    if puuid == puuid_of(NEWBIE_NAME) {
+        return json("0".to_string());
+    }
+    json(MASTERY_SCORE.to_string())
+}
+
+/// Newest first like Riot, truncated to the requested `count`
+async fn match_ids(
+    State(state): State<MockState>,
+    Path((_, puuid)): Path<(String, String)>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    if puuid == puuid_of(NEWBIE_NAME) {
+        return json("[]".to_string());
+    }
+    if puuid == puuid_of(UNSTABLE_NAME) && count_lookup(&state, format!("matches:{}", puuid)) > 1 {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable").into_response();
+    }
+    let count = query.get("count").and_then(|count| count.parse().ok()).unwrap_or(20);
+    let ids: Vec<String> = serde_json::from_str(MATCH_IDS).unwrap();
+    json(Value::from(ids.into_iter().take(count).collect::<Vec<_>>()).to_string())
+}
+
+/// The fixture match, renumbered to the requested id so every id gets a distinct game
+async fn riot_match(Path((_, match_id)): Path<(String, String)>) -> Response {
+    let Some(game_id) = match_id.rsplit_once('_').and_then(|(_, id)| id.parse::<u64>().ok()) else {
+        return not_found();
+    };
+    let mut riot_match: Value = serde_json::from_str(MATCH).unwrap();
+    riot_match["metadata"]["matchId"] = Value::from(match_id);
+    riot_match["info"]["gameId"] = Value::from(game_id);
+    json(riot_match.to_string())
+}
+
+async fn active_game(Path((_, puuid)): Path<(String, String)>) -> Response {
+    if puuid != puuid_of(IN_GAME_NAME) {
+        return not_found();
+    }
+    json(ACTIVE_GAME.to_string())
+}
+
+async fn player_challenges(State(state): State<MockState>, Path((_, puuid)): Path<(String, String)>) -> Response {
+    if puuid == puuid_of(NEWBIE_NAME) {
+        return json(
+            r#"{"challenges":[],"totalPoints":{"level":"NONE","current":0,"max":38870},"categoryPoints":{}}"#
+                .to_string(),
+        );
+    }
+    let lookups = {
+        let mut attempts = state.attempts.lock().unwrap();
+        let lookups = attempts.entry(format!("challenges:{}", puuid)).or_default();
+        *lookups += 1;
+        *lookups
+    };
+    if puuid == puuid_of(UNSTABLE_NAME) && lookups > 1 {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Service unavailable").into_response();
+    }
+    json(PLAYER_CHALLENGES.to_string())
+}
+
+async fn challenge_percentiles() -> Response {
+    json(CHALLENGE_PERCENTILES.to_string())
+}
+
+/// Counts one more lookup for `key` and returns the new total
+fn count_lookup(state: &MockState, key: String) -> usize {
+    let mut attempts = state.attempts.lock().unwrap();
+    let lookups = attempts.entry(key).or_default();
+    *lookups += 1;
+    *lookups
+}
+
+fn json(body: String) -> Response {
+    ([(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+fn not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        r#"{"status":{"message":"Data not found","status_code":404}}"#,
+    )
+        .into_response()
+}
+
+fn forbidden() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        r#"{"status":{"message":"Forbidden","status_code":403}}"#,
+    )
+        .into_response()
+}
+
+fn too_many_requests() -> Response {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [("Retry-After", "1"), ("X-Rate-Limit-Type", "method")],
+        r#"{"status":{"message":"Rate limit exceeded","status_code":429}}"#,
+    )
+        .into_response()
+}
+
+/// Sends one request through the full router and returns status, headers and parsed JSON body
+pub async fn send(state: &AppState, request: Request<Body>) -> (StatusCode, HeaderMap, Value) {
+    let response = router(state.clone()).oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let json = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    (status, headers, json)
+}
+
+pub async fn get_text(state: &AppState, uri: &str) -> String {
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+    let response = router(state.clone()).oneshot(request).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+pub async fn get(state: &AppState, uri: &str) -> (StatusCode, HeaderMap, Value) {
+    send(state, Request::get(uri).body(Body::empty()).unwrap()).await
+}
+
+pub async fn post_json(state: &AppState, uri: &str, body: Value) -> (StatusCode, HeaderMap, Value) {
+    let request = Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    send(state, request).await
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use common::*;
+use sedidata_server::AppState;
+use serde_json::json;
+
+const KEY: &str = "test-key";
+
+fn count(requests: &[String], path: &str) -> usize {
+    requests.iter().filter(|request| request.ends_with(path)).count()
+}
+
+#[tokio::test]
+async fn mastery_list_returns_score_and_top_champions() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/mastery?name=Faker&tagline=KR1&count=2&platform=kr").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["score"], MASTERY_SCORE);
+    let masteries = body["masteries"].as_array().unwrap();
+    assert_eq!(masteries.len(), 2);
+    assert_eq!(masteries[0]["championId"], 157);
+    assert_eq!(masteries[0]["championLevel"], 42);
+    assert_eq!(masteries[0]["championPoints"], 487310);
+    assert_eq!(masteries[1]["championId"], 7);
+    let requests = mock.requests();
+    assert_eq!(
+        count(
+            &requests,
+            "/kr/lol/champion-mastery/v4/champion-masteries/by-puuid/puuid-faker"
+        ),
+        1
+    );
+    assert_eq!(
+        count(&requests, "/kr/lol/champion-mastery/v4/scores/by-puuid/puuid-faker"),
+        1
+    );
+}
+
+#[tokio::test]
+async fn mastery_list_is_cached_for_every_count() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    get(&state, "/mastery?name=Faker&tagline=KR1&count=1").await;
+    let (status, _, body) = get(&state, "/mastery?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["masteries"].as_array().unwrap().len(), 3);
+    let requests = mock.requests();
+    assert_eq!(count(&requests, "/champion-masteries/by-puuid/puuid-faker"), 1);
+    assert_eq!(count(&requests, "/scores/by-puuid/puuid-faker"), 1);
+}
+
+#[tokio::test]
+async fn player_without_masteries_gets_an_empty_list() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = format!("/mastery?name={}&tagline=EUW", NEWBIE_NAME);
+    let (status, _, body) = get(&state, &uri).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "score": 0, "masteries": [] }));
+}
+
+#[tokio::test]
+async fn expired_mastery_list_is_not_served_when_riot_fails() {
+    let mock = MockRiot::start().await;
+    let mut config = mock.config();
+    config.ttls.mastery_list = Duration::ZERO;
+    let state = AppState::new(config, vec![KEY.to_string()]).unwrap();
+    let uri = format!("/mastery?name={}&tagline=EUW", UNSTABLE_NAME);
+
+    let (status, _, _) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, body) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["code"], "upstream_unavailable");
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use axum::http::StatusCode;
+use common::*;
+use serde_json::json;
+
+const KEY: &str = "test-key";
+
+fn count(requests: &[String], path: &str) -> usize {
+    requests.iter().filter(|request| request.contains(path)).count()
+}
+
+#[tokio::test]
+async fn recent_matches_are_summarized_for_the_player() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let (status, _, body) = get(&state, "/matches?name=Faker&tagline=KR1&count=2").await;
+
+    assert_eq!(status, StatusCode::OK);
+    let matches = body.as_array().unwrap();
+    assert_eq!(matches.len(), 2);
+    // Newest first, in the order Riot listed the ids
+    assert_eq!(matches[0]["game_id"], 7000000003u64);
+    assert_eq!(matches[1]["game_id"], 7000000002u64);
+    assert_eq!(matches[0]["champion_name"], "Yasuo");
+    assert_eq!(matches[0]["position"], "MIDDLE");
+    assert_eq!((&matches[0]["kills"], &matches[0]["deaths"]), (&json!(8), &json!(3)));
+    assert_eq!(matches[0]["cs"], 243);
+    assert_eq!(matches[0]["duration_seconds"], 1845);
+    assert_eq!(matches[0]["win"], true);
+    // Match-v5 is served by the regional host of the platform
+    assert!(mock
+        .requests()
+        .contains(&"/europe/lol/match/v5/matches/by-puuid/puuid-faker/ids".to_string()));
+}
+
+#[tokio::test]
+async fn match_details_are_cached_but_match_ids_are_not() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    get(&state, "/matches?name=Faker&tagline=KR1").await;
+    let (status, _, body) = get(&state, "/matches?name=Faker&tagline=KR1").await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 3);
+    let requests = mock.requests();
+    assert_eq!(count(&requests, "/lol/match/v5/matches/by-puuid/"), 2);
+    assert_eq!(count(&requests, "/lol/match/v5/matches/EUW1_"), 3);
+}
+
+#[tokio::test]
+async fn player_without_matches_gets_an_empty_list() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = format!("/matches?name={}&tagline=EUW", NEWBIE_NAME);
+    let (status, _, body) = get(&state, &uri).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!([]));
+}
+
+#[tokio::test]
+async fn match_list_fails_when_riot_fails() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+    let uri = format!("/matches?name={}&tagline=EUW", UNSTABLE_NAME);
+
+    let (status, _, _) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Match ids are never cached, so there is nothing to fall back to
+    let (status, _, body) = get(&state, &uri).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+    assert_eq!(body["code"], "upstream_unavailable");
+}
+
+#[tokio::test]
+async fn match_list_of_unknown_riot_id_is_not_found() {
+    let mock = MockRiot::start().await;
+    let state = mock.app_state(KEY);
+
+    let uri = format!("/matches?name={}&tagline=EUW", MISSING_NAME);
+    let (status, _, body) = get(&state, &uri).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["code"], "account_not_found");
+    assert_eq!(count(&mock.requests(), "/lol/match/v5/"), 0);
+}
@@ -1,3 +1,7 @@
+use std::collections::HashMap;
+
+use super::summoner::SummonerName;
+
 #[derive(Clone, Debug)]
 pub struct Challenge {
     pub id: i32,
@@ -28,6 +32,21 @@ pub const LEVELS: [&str; 10] = [
     "CHALLENGER",
 ];
 
+/// Share of players (0.0 - 1.0) that reached each level, by challenge id
+pub type ChallengePercentiles = HashMap<i32, HashMap<String, f64>>;
+
+/// Total challenge points of a player, compared with friends in the overview
+#[derive(Clone, Debug)]
+pub struct ChallengeTotals {
+    pub name: SummonerName,
+    pub is_self: bool,
+    pub level: String,
+    pub current_points: u64,
+    pub max_points: u64,
+    pub percentile: Option<f64>,
+    pub lookup_error: Option<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Threshold {
     pub level: String,
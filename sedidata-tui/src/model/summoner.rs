@@ -35,11 +35,29 @@ pub struct Summoner {
     pub level: Option<u16>,
 }
 
+/// Age of data returned by the sedidata server
+#[derive(Debug, Clone, Copy)]
+pub struct DataFreshness {
+    pub age_secs: u64,
+    /// The server could not refresh the data and returned an outdated copy
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SummonerFreshness {
+    pub level: DataFreshness,
+    pub ranked_stats: DataFreshness,
+    pub champion_mastery: Option<DataFreshness>,
+}
+
 #[derive(Debug, Clone)]
 pub struct RiotApiSummonerResponse {
+    /// Riot ID with the capitalization chosen by the player
+    pub riot_id: SummonerName,
     pub level: u16,
     pub ranked_stats: Vec<RankedQueueStats>,
     pub champion_mastery_info: Option<(u16, u32)>,
+    pub freshness: SummonerFreshness,
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +65,10 @@ pub struct SummonerWithStats {
     pub summoner: Summoner,
     pub ranked_stats: Option<HashMap<String, RankedQueueStats>>,
     pub champion_mastery: PlayedChampionMasteryInfo,
+    /// Current Riot ID as reported by the server, may differ from `summoner.name` in capitalization
+    pub riot_id: Option<SummonerName>,
+    pub freshness: Option<SummonerFreshness>,
+    pub lookup_error: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -12,7 +12,7 @@ use itertools::Itertools;
 
 use crate::{
     model::{
-        challenge::Challenge,
+        challenge::{Challenge, ChallengePercentiles, ChallengeTotals},
         champion::{AllChampionInfo, Champion, Chroma, Skin},
         game::{ChampSelectSession, LiveGameSession, PostGameSession, QueueInfo},
         loot::LootItems,
@@ -31,10 +31,10 @@ use crate::{
             mastery::parse_masteries,
             postgame::parse_post_game,
             queues::parse_queues,
-            summoner::{parse_ranked_stats, parse_summoner},
+            summoner::{parse_friends, parse_ranked_stats, parse_summoner},
             ParsingError,
         },
-        riot_api_client::{RiotApiClient, RiotApiClientInitError, RiotApiRequestError},
+        riot_api_client::{RiotApiClient, RiotApiClientInitError, RiotApiRequestError, ServerStatus},
     },
 };
 pub struct DataManager {
@@ -46,6 +46,7 @@ pub struct DataManager {
     masteries_cache: Arc<Mutex<Option<Vec<Mastery>>>>,
     loot_cache: Arc<Mutex<Option<LootItems>>>,
     challenges_cache: Arc<Mutex<Option<Vec<Challenge>>>>,
+    challenge_percentiles_cache: Arc<Mutex<Option<ChallengePercentiles>>>,
     queues_cache: Arc<Mutex<Option<Vec<QueueInfo>>>>,
     store_responses: Arc<Mutex<bool>>,
 }
@@ -68,11 +69,16 @@ impl DataManager {
             masteries_cache: Arc::new(Mutex::new(None)),
             loot_cache: Arc::new(Mutex::new(None)),
             challenges_cache: Arc::new(Mutex::new(None)),
+            challenge_percentiles_cache: Arc::new(Mutex::new(None)),
             queues_cache: Arc::new(Mutex::new(None)),
             store_responses,
         })
     }
 
+    pub fn get_server_status(&self) -> ServerStatus {
+        self.riot_api_client.server_status()
+    }
+
     pub fn get_store_responses(&self) -> bool {
         *self.store_responses.lock().unwrap()
     }
@@ -219,6 +225,78 @@ impl DataManager {
         })
     }
 
+    pub fn get_challenge_percentiles(&self) -> Receiver<DataRetrievalResult<ChallengePercentiles>> {
+        let riot_client = Arc::clone(&self.riot_api_client);
+        let cache = Arc::clone(&self.challenge_percentiles_cache);
+
+        self.async_wrapper(move || {
+            let mut cache_guard = cache.lock().unwrap();
+
+            if let Some(percentiles) = cache_guard.as_ref() {
+                return Ok(percentiles.clone());
+            }
+
+            let response = riot_client.get_challenge_percentiles()?;
+            let percentiles: ChallengePercentiles = response
+                .percentiles
+                .into_iter()
+                .filter_map(|(id, levels)| i32::try_from(id).ok().map(|id| (id, levels.into_iter().collect())))
+                .collect();
+
+            *cache_guard = Some(percentiles.clone());
+            Ok(percentiles)
+        })
+    }
+
+    /// Challenge totals of the summoner and all friends with a Riot ID, most points first.
+    /// Players the server cannot look up (e.g. on another platform) are kept with their error, after everyone else
+    pub fn get_challenge_comparison(&self) -> Receiver<DataRetrievalResult<Vec<ChallengeTotals>>> {
+        let client = Arc::clone(&self.lcu_client);
+        let riot_client = Arc::clone(&self.riot_api_client);
+        let own_name = self.get_summoner().name;
+
+        self.async_wrapper(move || {
+            let friends_json = client.request(LcuClientRequestType::Friends, true)?;
+            let mut players = vec![own_name.clone()];
+            players.extend(
+                parse_friends(Arc::as_ref(&friends_json))?
+                    .into_iter()
+                    .filter(|name| *name != own_name),
+            );
+
+            let mut totals = riot_client
+                .get_multiple_challenge_totals(&players)
+                .into_iter()
+                .map(|(name, response)| {
+                    let is_self = name == own_name;
+                    match response {
+                        Ok(challenges) => ChallengeTotals {
+                            name,
+                            is_self,
+                            level: challenges.total_points.level,
+                            current_points: challenges.total_points.current,
+                            max_points: challenges.total_points.max,
+                            percentile: challenges.total_points.percentile,
+                            lookup_error: None,
+                        },
+                        Err(err) => ChallengeTotals {
+                            name,
+                            is_self,
+                            level: String::new(),
+                            current_points: 0,
+                            max_points: 0,
+                            percentile: None,
+                            lookup_error: Some(err.short_description().to_string()),
+                        },
+                    }
+                })
+                .collect_vec();
+            totals.sort_by_key(|player| std::cmp::Reverse(player.current_points));
+
+            Ok(totals)
+        })
+    }
+
     pub fn get_queue_types(&self) -> Receiver<DataRetrievalResult<Vec<QueueInfo>>> {
         let client = Arc::clone(&self.lcu_client);
         let cache = Arc::clone(&self.queues_cache);
@@ -290,14 +368,14 @@ impl DataManager {
             let riot_api_response = riot_client.get_multiple_player_info(&players.iter().cloned().collect_vec());
 
             let mut results = Vec::new();
-            for (name, response_json) in riot_api_response {
-                if let Ok(json) = &response_json {
-                    if let Ok(parsed) = parse_ranked_stats(json.as_ref()) {
-                        results.push((name, Some(parsed)));
-                        continue;
-                    }
+            for (name, response) in riot_api_response {
+                match &response {
+                    Ok(league) => match parse_ranked_stats(league) {
+                        Ok(parsed) => results.push((name, Some(parsed), None)),
+                        Err(_) => results.push((name, None, None)),
+                    },
+                    Err(err) => results.push((name, None, Some(err.short_description().to_string()))),
                 }
-                results.push((name, None));
             }
 
             let champion_name_lookup: HashMap<_, _> = players
@@ -308,8 +386,7 @@ impl DataManager {
             // Map to SummonerWithStats and return
             Ok(results
                 .into_iter()
-                .filter_map(|(name, resp)| {
-                    let summ_name = name?;
+                .map(|(summ_name, resp, lookup_error)| {
                     let summoner = Summoner {
                         id: 0.into(),
                         puuid: "".into(),
@@ -317,7 +394,7 @@ impl DataManager {
                         level: resp.clone().map(|r| r.level),
                     };
                     let champion_name = champion_name_lookup.get(&summ_name).cloned();
-                    Some(SummonerWithStats {
+                    SummonerWithStats {
                         summoner,
                         ranked_stats: resp.as_ref().map(|r| {
                             r.ranked_stats
@@ -329,7 +406,10 @@ impl DataManager {
                             champion_name,
                             level_points: resp.as_ref().and_then(|r| r.champion_mastery_info),
                         },
-                    })
+                        riot_id: resp.as_ref().map(|r| r.riot_id.clone()),
+                        freshness: resp.as_ref().map(|r| r.freshness),
+                        lookup_error,
+                    }
                 })
                 .collect_vec())
         })
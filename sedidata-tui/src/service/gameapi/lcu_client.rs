@@ -240,6 +240,9 @@ impl LcuClient {
                     }
                     LcuClientRequestType::EndOfGame => {
                         format!("{}lol-end-of-game/v1/eog-stats-block", self.base_url)
+                    }
+                    LcuClientRequestType::Friends => {
+                        format!("{}lol-chat/v1/friends", self.base_url)
                     } // LcuClientRequestType::OtherSummoner(puuid) => {
                       //     format!("{}lol-summoner/v2/summoners/puuid/{}", self.base_url, puuid)
                       // }
@@ -411,6 +414,7 @@ pub enum LcuClientRequestType {
     Challenges,
     QueueTypes,
     EndOfGame,
+    Friends,
 }
 
 impl fmt::Display for LcuClientRequestType {
@@ -424,6 +428,7 @@ impl fmt::Display for LcuClientRequestType {
             LcuClientRequestType::Challenges => write!(f, "Challenges"),
             LcuClientRequestType::QueueTypes => write!(f, "QueueTypes"),
             LcuClientRequestType::EndOfGame => write!(f, "EndOfGame"),
+            LcuClientRequestType::Friends => write!(f, "Friends"),
         }
     }
 }
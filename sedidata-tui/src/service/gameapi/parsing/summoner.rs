@@ -1,6 +1,9 @@
 use json::JsonValue;
+use sedidata_common::{Freshness, LeagueResponse};
 
-use crate::model::summoner::{RankedQueueStats, RiotApiSummonerResponse, Summoner, SummonerName};
+use crate::model::summoner::{
+    DataFreshness, RankedQueueStats, RiotApiSummonerResponse, Summoner, SummonerFreshness, SummonerName,
+};
 
 use super::ParsingError;
 
@@ -34,75 +37,81 @@ pub fn parse_summoner(json: &JsonValue) -> Result<Summoner, ParsingError> {
     Err(ParsingError::InvalidType("root".into()))
 }
 
-pub fn parse_ranked_stats(json: &JsonValue) -> Result<RiotApiSummonerResponse, ParsingError> {
-    if let JsonValue::Object(obj) = &json {
-        let level = obj["level"].as_u16().ok_or(ParsingError::InvalidType("level".into()))?;
-
-        let mut stats = Vec::new();
-        if let JsonValue::Array(queues_array) = &obj["ranked_stats"] {
-            for queue_json in queues_array {
-                if let JsonValue::Object(queue) = queue_json {
-                    let queue_type = queue["queueType"]
-                        .as_str()
-                        .ok_or(ParsingError::InvalidType("queueType".into()))?
-                        .to_string();
-
-                    // Skip non-SR queues
-                    if queue_type.contains("TFT") {
-                        continue;
-                    }
-
-                    let tier = queue["tier"]
-                        .as_str()
-                        .ok_or(ParsingError::InvalidType("tier".into()))?
-                        .to_string();
-
-                    let division = queue["rank"]
-                        .as_str()
-                        .ok_or(ParsingError::InvalidType("division".into()))?
-                        .to_string();
-
-                    let league_points = queue["leaguePoints"]
-                        .as_u32()
-                        .ok_or(ParsingError::InvalidType("leaguePoints".into()))?;
-
-                    let wins = queue["wins"].as_u32().ok_or(ParsingError::InvalidType("wins".into()))?;
-
-                    let losses = queue["losses"]
-                        .as_u32()
-                        .ok_or(ParsingError::InvalidType("losses".into()))?;
-
-                    stats.push(RankedQueueStats {
-                        queue_type,
-                        tier,
-                        division,
-                        league_points,
-                        wins,
-                        losses,
-                    });
-                }
+/// Riot IDs of all friends, friends without one (e.g. pending requests) are left out
+pub fn parse_friends(json: &JsonValue) -> Result<Vec<SummonerName>, ParsingError> {
+    let mut friends = Vec::new();
+
+    for friend in json.members() {
+        if let JsonValue::Object(friend_obj) = &friend {
+            // Friends without a Riot ID (e.g. from other games) can not be looked up, skip them
+            let (Some(game_name), Some(tag_line)) = (friend_obj["gameName"].as_str(), friend_obj["gameTag"].as_str())
+            else {
+                continue;
+            };
+            if game_name.is_empty() || tag_line.is_empty() {
+                continue;
             }
+
+            friends.push(SummonerName {
+                game_name: game_name.to_string(),
+                tag_line: tag_line.to_string(),
+            });
+        } else {
+            return Err(ParsingError::InvalidType("friends".into()));
         }
+    }
 
-        let mut champion_mastery_info = None;
-        if let JsonValue::Object(mastery_info) = &obj["champion_mastery"] {
-            let champion_level = mastery_info["championLevel"]
-                .as_u16()
-                .ok_or(ParsingError::InvalidType("championLevel".into()))?;
+    Ok(friends)
+}
 
-            let champion_points = mastery_info["championPoints"]
-                .as_u32()
-                .ok_or(ParsingError::InvalidType("championPoints".into()))?;
+pub fn parse_ranked_stats(league: &LeagueResponse) -> Result<RiotApiSummonerResponse, ParsingError> {
+    let level = u16::try_from(league.level).map_err(|_| ParsingError::InvalidType("level".into()))?;
 
-            champion_mastery_info = Some((champion_level, champion_points));
+    let mut stats = Vec::new();
+    for queue in &league.ranked_stats {
+        // Skip non-SR queues
+        if queue.queue_type.contains("TFT") {
+            continue;
         }
 
-        return Ok(RiotApiSummonerResponse {
-            level,
-            ranked_stats: stats,
-            champion_mastery_info,
+        stats.push(RankedQueueStats {
+            queue_type: queue.queue_type.clone(),
+            tier: queue.tier.clone(),
+            division: queue.rank.clone(),
+            league_points: u32::try_from(queue.league_points)
+                .map_err(|_| ParsingError::InvalidType("leaguePoints".into()))?,
+            wins: u32::try_from(queue.wins).map_err(|_| ParsingError::InvalidType("wins".into()))?,
+            losses: u32::try_from(queue.losses).map_err(|_| ParsingError::InvalidType("losses".into()))?,
         });
     }
 
-    Err(ParsingError::InvalidType("root".into()))
+    let champion_mastery_info = match &league.champion_mastery {
+        Some(mastery) => Some((
+            u16::try_from(mastery.level).map_err(|_| ParsingError::InvalidType("championLevel".into()))?,
+            u32::try_from(mastery.points).map_err(|_| ParsingError::InvalidType("championPoints".into()))?,
+        )),
+        None => None,
+    };
+
+    Ok(RiotApiSummonerResponse {
+        riot_id: SummonerName {
+            game_name: league.riot_id.game_name.clone(),
+            tag_line: league.riot_id.tag_line.clone(),
+        },
+        level,
+        ranked_stats: stats,
+        champion_mastery_info,
+        freshness: SummonerFreshness {
+            level: convert_freshness(&league.freshness.level),
+            ranked_stats: convert_freshness(&league.freshness.ranked_stats),
+            champion_mastery: league.freshness.champion_mastery.as_ref().map(convert_freshness),
+        },
+    })
+}
+
+fn convert_freshness(freshness: &Freshness) -> DataFreshness {
+    DataFreshness {
+        age_secs: freshness.age_secs.max(0) as u64,
+        stale: freshness.stale,
+    }
 }
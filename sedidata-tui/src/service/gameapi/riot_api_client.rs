@@ -1,114 +1,308 @@
 use std::{
-    fmt,
-    sync::{mpsc, Arc},
+    env, fmt, fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
     thread,
     time::Duration,
 };
 
+use itertools::Itertools;
 use json::JsonValue;
-use reqwest::blocking::Client;
+use reqwest::{
+    blocking::Client,
+    header::{self, HeaderMap, HeaderValue},
+};
+use sedidata_common::{
+    BatchPlayerError, ChallengePercentilesResponse, ChallengesBatchEntry, ChallengesResponse, KeyStatus,
+    LeagueBatchEntry, LeagueResponse, ReadyResponse, UpstreamStatus,
+};
 
 use crate::model::{champion::Champion, ids::ChampionId, summoner::SummonerName};
 
 const BASE_URL: &str = "https://sedidata-server.onrender.com";
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5 * 60); // 5 minutes
+/// API token for servers with authentication, takes precedence over the token file
+const TOKEN_ENV: &str = "SEDIDATA_TOKEN";
+/// File in the app data directory holding the API token
+const TOKEN_FILE: &str = "token.txt";
+/// Largest batch sedidata-server accepts
+const MAX_BATCH_SIZE: usize = 20;
 
 pub struct RiotApiClient {
     client: Client,
+    status: Arc<Mutex<ServerStatus>>,
 }
 
 impl RiotApiClient {
     pub fn new() -> Result<Self, RiotApiClientInitError> {
-        let client = Client::builder().timeout(Duration::from_secs(90)).build()?;
+        let mut headers = HeaderMap::new();
+        if let Some(token) = Self::api_token() {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| RiotApiClientInitError::InvalidToken)?;
+            value.set_sensitive(true);
+            headers.insert(header::AUTHORIZATION, value);
+        }
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(90))
+            .default_headers(headers)
+            .build()?;
 
         // Clone for heartbeat thread
         let heartbeat_client = client.clone();
+        let status = Arc::new(Mutex::new(ServerStatus::Unknown));
+        let heartbeat_status = Arc::clone(&status);
 
         // Spawn heartbeat thread
         thread::spawn(move || {
-            Self::heartbeat_loop(heartbeat_client);
+            Self::heartbeat_loop(heartbeat_client, heartbeat_status);
         });
 
-        Ok(Self { client })
+        Ok(Self { client, status })
+    }
+
+    /// Status reported by the last heartbeat
+    pub fn server_status(&self) -> ServerStatus {
+        *self.status.lock().unwrap()
     }
 
-    fn heartbeat_loop(client: Client) {
+    fn api_token() -> Option<String> {
+        env::var(TOKEN_ENV)
+            .ok()
+            .filter(|token| !token.trim().is_empty())
+            .or_else(Self::read_token_file)
+    }
+
+    fn read_token_file() -> Option<String> {
+        let local_app_data = env::var("LOCALAPPDATA").ok()?;
+        let path = PathBuf::from(local_app_data).join("sedidata").join(TOKEN_FILE);
+        let token = fs::read_to_string(path).ok()?.trim().to_string();
+        (!token.is_empty()).then_some(token)
+    }
+
+    fn heartbeat_loop(client: Client, status: Arc<Mutex<ServerStatus>>) {
         loop {
-            let url = format!("{}/heartbeat", BASE_URL);
-            let _is_alive = match client.get(&url).send() {
-                Ok(response) => response.status().is_success(),
-                Err(_) => false,
+            let url = format!("{}/ready", BASE_URL);
+            let current = match client.get(&url).send() {
+                // Not being ready is answered with 503, the body tells why
+                Ok(response) => response
+                    .text()
+                    .ok()
+                    .and_then(|body| serde_json::from_str::<ReadyResponse>(&body).ok())
+                    .map_or(ServerStatus::Unknown, |ready| ServerStatus::from_ready(&ready)),
+                Err(_) => ServerStatus::Offline,
             };
+            *status.lock().unwrap() = current;
 
             thread::sleep(HEARTBEAT_INTERVAL);
         }
     }
 
+    /// Looks up all players with a Riot ID, private players cannot be looked up and are left out
     pub fn get_multiple_player_info(
         &self,
         players: &[(Option<SummonerName>, Option<Champion>)],
-    ) -> Vec<(Option<SummonerName>, RiotApiClientResult<Arc<JsonValue>>)> {
-        let (tx, rx) = mpsc::channel();
+    ) -> Vec<(SummonerName, RiotApiClientResult<LeagueResponse>)> {
+        let named_players = players
+            .iter()
+            .filter_map(|(name, champ)| name.clone().map(|n| (n, champ.as_ref().map(|c| c.id.clone()))))
+            .collect_vec();
 
-        // Spawn a thread for each request
-        for (name, champ) in players {
-            let client = self.client.clone();
-            let tx = tx.clone();
-            let name = name.clone();
-            let champ = champ.clone();
+        let mut results = Vec::new();
+        for chunk in named_players.chunks(MAX_BATCH_SIZE) {
+            let names = chunk.iter().map(|(name, _)| name.clone());
+            match Self::fetch_player_info_batch(&self.client, chunk) {
+                Ok(entries) => results.extend(names.zip(entries)),
+                Err(err) => results.extend(names.map(|name| (name, Err(err.for_player())))),
+            }
+        }
 
-            thread::spawn(move || {
-                let result = Self::fetch_player_info(&client, &name, &champ.as_ref().map(|c| c.id.clone()));
-                let _ = tx.send((name, result));
-            });
+        results
+    }
+
+    /// Challenge totals of all players, in the order of `players`
+    pub fn get_multiple_challenge_totals(
+        &self,
+        players: &[SummonerName],
+    ) -> Vec<(SummonerName, RiotApiClientResult<ChallengesResponse>)> {
+        let mut results = Vec::new();
+        for chunk in players.chunks(MAX_BATCH_SIZE) {
+            match Self::fetch_challenges_batch(&self.client, chunk) {
+                Ok(entries) => results.extend(chunk.iter().cloned().zip(entries)),
+                Err(err) => results.extend(chunk.iter().map(|name| (name.clone(), Err(err.for_player())))),
+            }
         }
 
-        // Drop the original sender so rx knows when all threads are done
-        drop(tx);
+        results
+    }
+
+    /// Share of players that reached each level, by challenge id
+    pub fn get_challenge_percentiles(&self) -> RiotApiClientResult<ChallengePercentilesResponse> {
+        let url = format!("{}/v2/challenges/percentiles", BASE_URL);
+        let response = self.client.get(&url).send()?;
 
-        // Collect all results
-        rx.into_iter().collect()
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Self::decode_error(status, &body));
+        }
+
+        let text = response.text()?;
+        Ok(serde_json::from_str(&text)?)
     }
 
-    fn fetch_player_info(
+    fn fetch_player_info_batch(
         client: &Client,
-        name: &Option<SummonerName>,
-        champ: &Option<ChampionId>,
-    ) -> RiotApiClientResult<Arc<JsonValue>> {
-        let Some(summ_name) = name else {
-            return Ok(Arc::new(JsonValue::Null));
-        };
+        players: &[(SummonerName, Option<ChampionId>)],
+    ) -> RiotApiClientResult<Vec<RiotApiClientResult<LeagueResponse>>> {
+        let mut body = JsonValue::new_array();
+        for (name, champ) in players {
+            let mut entry = json::object! {
+                name: name.game_name.clone(),
+                tagline: name.tag_line.clone(),
+            };
+            if let Some(champ_id) = champ {
+                entry["champion"] = champ_id.0.clone().into();
+            }
+            body.push(entry)?;
+        }
+
+        let url = format!("{}/v2/league/batch", BASE_URL);
+        let response = client
+            .post(&url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.dump())
+            .send()?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Self::decode_error(status, &body));
+        }
 
-        let mut url = format!(
-            "{}/league?name={}&tagline={}",
-            BASE_URL,
-            urlencoding::encode(&summ_name.game_name),
-            urlencoding::encode(&summ_name.tag_line)
-        );
+        let text = response.text()?;
+        let entries: Vec<LeagueBatchEntry> = serde_json::from_str(&text)?;
+        Self::check_entry_count(entries.len(), players.len())?;
+
+        // Server keeps the order of the request, one entry per player
+        Ok(entries
+            .into_iter()
+            .map(|entry| Self::entry_result(entry.data, entry.error))
+            .collect())
+    }
 
-        if let Some(champ_id) = champ {
-            url.push_str(&format!("&champion={}", champ_id.0));
+    fn fetch_challenges_batch(
+        client: &Client,
+        players: &[SummonerName],
+    ) -> RiotApiClientResult<Vec<RiotApiClientResult<ChallengesResponse>>> {
+        let mut body = JsonValue::new_array();
+        for name in players {
+            body.push(json::object! {
+                name: name.game_name.clone(),
+                tagline: name.tag_line.clone(),
+            })?;
         }
 
-        let response = client.get(&url).send()?;
+        let url = format!("{}/v2/challenges/batch", BASE_URL);
+        let response = client
+            .post(&url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body.dump())
+            .send()?;
 
         if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let body = response.text().unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Self::decode_error(status, &body));
+        }
+
+        let text = response.text()?;
+        let entries: Vec<ChallengesBatchEntry> = serde_json::from_str(&text)?;
+        Self::check_entry_count(entries.len(), players.len())?;
+
+        Ok(entries
+            .into_iter()
+            .map(|entry| Self::entry_result(entry.data, entry.error))
+            .collect())
+    }
+
+    /// Entries are matched to players by position, so a batch response must have one entry per player
+    fn check_entry_count(entries: usize, players: usize) -> RiotApiClientResult<()> {
+        if entries != players {
             return Err(RiotApiRequestError::InvalidResponse(
-                response.status().as_u16(),
-                response.text().unwrap_or_else(|_| "Unknown error".to_string()),
+                200,
+                format!("Expected {} batch entries, got {}", players, entries),
             ));
         }
+        Ok(())
+    }
 
-        let text = response.text()?;
-        let json = json::parse(&text)?;
+    /// Result of one player in a batch response
+    fn entry_result<T>(data: Option<T>, error: Option<BatchPlayerError>) -> RiotApiClientResult<T> {
+        match (data, error) {
+            (_, Some(error)) => Err(RiotApiRequestError::ServerError {
+                status: error.status,
+                code: ServerErrorCode::parse(&error.code),
+                message: error.error,
+            }),
+            (Some(data), None) => Ok(data),
+            (None, None) => Err(RiotApiRequestError::InvalidResponse(
+                200,
+                "Entry without data".to_string(),
+            )),
+        }
+    }
 
-        Ok(Arc::new(json))
+    fn decode_error(status: u16, body: &str) -> RiotApiRequestError {
+        match json::parse(body) {
+            Ok(json) if json["code"].is_string() => RiotApiRequestError::ServerError {
+                status,
+                code: ServerErrorCode::parse(json["code"].as_str().unwrap_or_default()),
+                message: json["error"].as_str().unwrap_or("Unknown error").to_string(),
+            },
+            _ => RiotApiRequestError::InvalidResponse(status, body.to_string()),
+        }
+    }
+}
+
+/// What the last heartbeat learned about the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerStatus {
+    /// No heartbeat answered yet, or the server is too old to report its status
+    Unknown,
+    Ready,
+    /// Server is up, but Riot rejects its API key
+    KeyExpired,
+    /// Server is up, but cannot reach the Riot API
+    UpstreamUnavailable,
+    Offline,
+}
+
+impl ServerStatus {
+    fn from_ready(ready: &ReadyResponse) -> Self {
+        match (ready.riot_api_key, ready.upstream) {
+            (KeyStatus::Invalid, _) => ServerStatus::KeyExpired,
+            (_, UpstreamStatus::Unreachable) => ServerStatus::UpstreamUnavailable,
+            (KeyStatus::Valid, _) => ServerStatus::Ready,
+            _ => ServerStatus::Unknown,
+        }
+    }
+
+    /// Why ranked data cannot be loaded, `None` if it should be available
+    pub fn unavailable_reason(&self) -> Option<&'static str> {
+        match self {
+            ServerStatus::Unknown | ServerStatus::Ready => None,
+            ServerStatus::KeyExpired => Some("server key expired"),
+            ServerStatus::UpstreamUnavailable => Some("Riot API unreachable"),
+            ServerStatus::Offline => Some("server offline"),
+        }
     }
 }
 
 #[derive(Debug)]
 pub enum RiotApiClientInitError {
     HttpClientCreation(reqwest::Error),
+    InvalidToken,
 }
 
 impl fmt::Display for RiotApiClientInitError {
@@ -117,6 +311,13 @@ impl fmt::Display for RiotApiClientInitError {
             RiotApiClientInitError::HttpClientCreation(e) => {
                 write!(f, "Failed to create HTTP client: {}", e)
             }
+            RiotApiClientInitError::InvalidToken => {
+                write!(
+                    f,
+                    "API token from {} or {} contains characters not allowed in an HTTP header",
+                    TOKEN_ENV, TOKEN_FILE
+                )
+            }
         }
     }
 }
@@ -129,11 +330,93 @@ impl From<reqwest::Error> for RiotApiClientInitError {
 
 pub type RiotApiClientResult<T> = Result<T, RiotApiRequestError>;
 
+/// Machine-readable error codes sent by sedidata-server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerErrorCode {
+    InvalidRequest,
+    AccountNotFound,
+    NotFound,
+    RateLimited,
+    KeyInvalid,
+    UpstreamUnavailable,
+    ParseFailed,
+    Unauthorized,
+    QuotaExceeded,
+    Unknown(String),
+}
+
+impl ServerErrorCode {
+    pub fn parse(code: &str) -> Self {
+        match code {
+            "invalid_request" => ServerErrorCode::InvalidRequest,
+            "account_not_found" => ServerErrorCode::AccountNotFound,
+            "not_found" => ServerErrorCode::NotFound,
+            "rate_limited" => ServerErrorCode::RateLimited,
+            "key_invalid" => ServerErrorCode::KeyInvalid,
+            "upstream_unavailable" => ServerErrorCode::UpstreamUnavailable,
+            "parse_failed" => ServerErrorCode::ParseFailed,
+            "unauthorized" => ServerErrorCode::Unauthorized,
+            "quota_exceeded" => ServerErrorCode::QuotaExceeded,
+            other => ServerErrorCode::Unknown(other.to_string()),
+        }
+    }
+
+    /// Short text that fits into a table cell
+    pub fn short_description(&self) -> &str {
+        match self {
+            ServerErrorCode::InvalidRequest => "Invalid name",
+            ServerErrorCode::AccountNotFound => "Not found",
+            ServerErrorCode::NotFound => "Not found",
+            ServerErrorCode::RateLimited => "Rate limited",
+            ServerErrorCode::KeyInvalid => "Key expired",
+            ServerErrorCode::UpstreamUnavailable => "Riot API down",
+            ServerErrorCode::ParseFailed => "Bad response",
+            ServerErrorCode::Unauthorized => "No access",
+            ServerErrorCode::QuotaExceeded => "Quota used up",
+            ServerErrorCode::Unknown(_) => "Server error",
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum RiotApiRequestError {
     NetworkError(reqwest::Error),
     InvalidResponse(u16, String),
+    ServerError {
+        status: u16,
+        code: ServerErrorCode,
+        message: String,
+    },
     JsonParseError(json::Error),
+    /// Valid JSON, but not in the shape of the shared response types
+    ResponseFormat(serde_json::Error),
+    BatchFailed(String),
+}
+
+impl RiotApiRequestError {
+    /// Error of a single player when the whole batch request failed
+    fn for_player(&self) -> Self {
+        match self {
+            RiotApiRequestError::ServerError { status, code, message } => RiotApiRequestError::ServerError {
+                status: *status,
+                code: code.clone(),
+                message: message.clone(),
+            },
+            other => RiotApiRequestError::BatchFailed(other.to_string()),
+        }
+    }
+
+    /// Short text that fits into a table cell
+    pub fn short_description(&self) -> &str {
+        match self {
+            RiotApiRequestError::NetworkError(_) => "Offline",
+            RiotApiRequestError::InvalidResponse(_, _) => "Server error",
+            RiotApiRequestError::ServerError { code, .. } => code.short_description(),
+            RiotApiRequestError::JsonParseError(_) => "Bad response",
+            RiotApiRequestError::ResponseFormat(_) => "Bad response",
+            RiotApiRequestError::BatchFailed(_) => "Lookup failed",
+        }
+    }
 }
 
 impl fmt::Display for RiotApiRequestError {
@@ -145,9 +428,18 @@ impl fmt::Display for RiotApiRequestError {
             RiotApiRequestError::InvalidResponse(status, body) => {
                 write!(f, "Server returned error {}: {}", status, body)
             }
+            RiotApiRequestError::ServerError { status, code, message } => {
+                write!(f, "Server returned error {} ({:?}): {}", status, code, message)
+            }
             RiotApiRequestError::JsonParseError(e) => {
                 write!(f, "Failed to parse JSON response: {}", e)
             }
+            RiotApiRequestError::ResponseFormat(e) => {
+                write!(f, "Unexpected response format: {}", e)
+            }
+            RiotApiRequestError::BatchFailed(msg) => {
+                write!(f, "Batch request failed: {}", msg)
+            }
         }
     }
 }
@@ -163,3 +455,9 @@ impl From<json::Error> for RiotApiRequestError {
         Self::JsonParseError(error)
     }
 }
+
+impl From<serde_json::Error> for RiotApiRequestError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::ResponseFormat(error)
+    }
+}
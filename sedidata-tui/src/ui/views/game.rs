@@ -7,7 +7,7 @@ use crate::{
         game::{ChampSelectSession, GameState, LiveGameSession, PlayerInfo, PostGameSession},
         ids::ChampionId,
         mastery::Mastery,
-        summoner::{SummonerName, SummonerWithStats},
+        summoner::{DataFreshness, SummonerName, SummonerWithStats},
     },
     service::lookup::LookupService,
     styled_line, styled_span,
@@ -156,6 +156,8 @@ pub struct LivePlayerInfoView {
     players_data: Option<AsyncData<Vec<SummonerWithStats>>>,
     game_state: Option<GameState>,
     self_info: SummonerName,
+    /// Reason from the last server heartbeat why ranked data cannot be loaded
+    ranked_unavailable: Option<&'static str>,
 }
 
 impl LivePlayerInfoView {
@@ -169,6 +171,7 @@ impl LivePlayerInfoView {
             game_state: None,
             players_data: None,
             self_info: summoner.name.clone(),
+            ranked_unavailable: ctrl.manager.get_server_status().unavailable_reason(),
         };
         view.start_session_requests(ctrl);
         view
@@ -255,6 +258,25 @@ impl LivePlayerInfoView {
         }
     }
 
+    /// Age suffix for cached values, yellow if the server could not refresh them, nothing for live data
+    fn format_freshness(freshness: Option<DataFreshness>) -> Option<ratatui::text::Span<'static>> {
+        let freshness = freshness?;
+        if !freshness.stale && freshness.age_secs < 60 {
+            return None;
+        }
+
+        let age = match freshness.age_secs {
+            secs if secs < 60 * 60 => format!("{}m", secs / 60),
+            secs if secs < 24 * 60 * 60 => format!("{}h", secs / (60 * 60)),
+            secs => format!("{}d", secs / (24 * 60 * 60)),
+        };
+        Some(if freshness.stale {
+            styled_span!(" {}!", age; Color::Yellow)
+        } else {
+            styled_span!(" {}", age; Color::DarkGray)
+        })
+    }
+
     fn format_champion_name(champion: &Option<String>) -> ratatui::text::Line<'_> {
         match champion {
             Some(champ) => styled_line!(champ.clone(); Color::White),
@@ -284,7 +306,8 @@ impl LivePlayerInfoView {
         summ_stats_opt: Option<&'b SummonerWithStats>,
     ) -> Vec<Row<'b>> {
         // Player info
-        let player_name = match &player.name {
+        // Prefer the Riot ID reported by the server, which has the capitalization chosen by the player
+        let player_name = match summ_stats_opt.and_then(|s| s.riot_id.as_ref()).or(player.name.as_ref()) {
             Some(sn) => styled_line!(sn.full(); Color::White),
             None => styled_line!("<Player is private>"; Color::DarkGray),
         };
@@ -298,7 +321,11 @@ impl LivePlayerInfoView {
             Cell::from(player_name),
             Cell::from(summ_stats_opt.map_or(styled_span!("?"; Color::DarkGray), |s| {
                 s.summoner.level.map_or(styled_span!("---"; Color::DarkGray), |level| {
-                    styled_span!(level.to_string())
+                    if s.freshness.is_some_and(|f| f.level.stale) {
+                        styled_span!(level.to_string(); Color::Yellow)
+                    } else {
+                        styled_span!(level.to_string())
+                    }
                 })
             })),
         ];
@@ -313,7 +340,10 @@ impl LivePlayerInfoView {
             Some(summ_stats) => match summ_stats.ranked_stats {
                 None => ranked_cells.extend([
                     vec![
-                        Cell::from(styled_span!("No data"; Color::DarkGray)),
+                        Cell::from(match &summ_stats.lookup_error {
+                            Some(error) => styled_span!(error.clone(); Color::Red),
+                            None => styled_span!("No data"; Color::DarkGray),
+                        }),
                         Cell::from(styled_span!("---"; Color::DarkGray)),
                         Cell::from(styled_span!("---"; Color::DarkGray)),
                         Cell::from(styled_span!("---"; Color::DarkGray)),
@@ -321,12 +351,16 @@ impl LivePlayerInfoView {
                     std::iter::repeat_n(Cell::from(""), 4).collect::<Vec<_>>(),
                 ]),
                 Some(ref ranked_stats) => {
+                    let age = Self::format_freshness(summ_stats.freshness.map(|f| f.ranked_stats));
                     for queue in &["RANKED_SOLO_5x5", "RANKED_FLEX_SR"] {
+                        let mut queue_spans = vec![styled_span!(Self::format_queue_type(queue))];
+                        queue_spans.extend(age.clone());
+                        let queue_type = styled_line!(VAR queue_spans);
                         match ranked_stats.get(*queue) {
                             Some(stats) => {
                                 let rank_color = Self::get_rank_color(&stats.tier);
                                 ranked_cells.push(vec![
-                                    Cell::from(Self::format_queue_type(queue)),
+                                    Cell::from(queue_type),
                                     Cell::from(
                                         styled_span!(Self::format_rank(&stats.tier, &stats.division); rank_color),
                                     ),
@@ -340,7 +374,7 @@ impl LivePlayerInfoView {
                                 ]);
                             }
                             None => ranked_cells.push(vec![
-                                Cell::from(Self::format_queue_type(queue)),
+                                Cell::from(queue_type),
                                 Cell::from(styled_span!("Unranked"; Color::DarkGray)),
                                 Cell::from(""),
                                 Cell::from(""),
@@ -354,10 +388,15 @@ impl LivePlayerInfoView {
         // Mastery
         let mastery_cells = match &summ_stats_opt.map(|s| &s.champion_mastery) {
             Some(mastery) => match mastery.level_points {
-                Some((level, points)) => (
-                    Cell::from(Self::format_champion_name(&mastery.champion_name)),
-                    Cell::from(styled_span!(Self::format_mastery(level, points); Color::White)),
-                ),
+                Some((level, points)) => {
+                    let age = Self::format_freshness(summ_stats_opt.and_then(|s| s.freshness?.champion_mastery));
+                    let mut spans = vec![styled_span!(Self::format_mastery(level, points); Color::White)];
+                    spans.extend(age);
+                    (
+                        Cell::from(Self::format_champion_name(&mastery.champion_name)),
+                        Cell::from(styled_line!(VAR spans)),
+                    )
+                }
                 None => (
                     Cell::from(Self::format_champion_name(&mastery.champion_name)),
                     Cell::from(styled_span!("---"; Color::DarkGray)),
@@ -398,6 +437,8 @@ impl RenderableView for LivePlayerInfoView {
     }
 
     fn update(&mut self, ctrl: &Controller, _keys: &[crossterm::event::KeyCode]) {
+        self.ranked_unavailable = ctrl.manager.get_server_status().unavailable_reason();
+
         // Update sources if they are active
         if let Some(cs_data) = &mut self.cs_data {
             cs_data.try_update();
@@ -749,9 +790,12 @@ impl RenderableView for LivePlayerInfoView {
                 rc.frame.render_widget(table, rc.area);
 
                 // Add hint text below the table (same horizontal area, one line from bottom)
-                let hint = styled_line!(
-                    "Note: Ranked / Mastery info may take up to a minute on first request."; Color::DarkGray
-                )
+                let hint = match self.ranked_unavailable {
+                    Some(reason) => styled_line!("Ranked data unavailable: {}", reason; Color::Yellow),
+                    None => styled_line!(
+                        "Note: Ranked / Mastery info may take up to a minute on first request."; Color::DarkGray
+                    ),
+                }
                 .alignment(Alignment::Center);
                 let hint_paragraph = Paragraph::new(vec![hint]).block(Block::default());
 
@@ -1,7 +1,8 @@
 use crate::{
     empty_row, fill_row, header_row,
-    model::challenge::Challenge,
+    model::challenge::{Challenge, ChallengePercentiles, ChallengeTotals},
     ui::{
+        async_data::AsyncData,
         views::{eval_color_scale_descending, RenderableView},
         Controller, RenderContext, ViewError, ViewResult,
     },
@@ -26,20 +27,29 @@ pub struct ChallengesOverviewView {
     categories: Vec<(String, Vec<Challenge>)>,
     error: Option<String>,
     sorting_state: u8,
+    percentiles: AsyncData<ChallengePercentiles>,
+    comparison: AsyncData<Vec<ChallengeTotals>>,
 }
 
 impl ChallengesOverviewView {
     pub fn new(controller: &Controller) -> Self {
+        let percentiles = AsyncData::new(controller.manager.get_challenge_percentiles());
+        let comparison = AsyncData::new(controller.manager.get_challenge_comparison());
+
         match Self::load_challenges(controller) {
             Ok(categories) => Self {
                 categories,
                 error: None,
                 sorting_state: 0,
+                percentiles,
+                comparison,
             },
             Err(e) => Self {
                 categories: Vec::new(),
                 error: Some(format!("Failed to load challenges: {}", e)),
                 sorting_state: 0,
+                percentiles,
+                comparison,
             },
         }
     }
@@ -78,13 +88,14 @@ impl ChallengesOverviewView {
         Ok(categories)
     }
 
-    fn columns(&self) -> [Constraint; 5] {
+    fn columns(&self) -> [Constraint; 6] {
         [
             Constraint::Length(32), // Name
             Constraint::Min(40),    // Description (takes remaining space)
             Constraint::Length(6),  // Game mode
             Constraint::Length(8),  // reward in pts
             Constraint::Length(12), // Progress (current/threshold)
+            Constraint::Length(7),  // Share of players with the current level
         ]
     }
 
@@ -114,12 +125,87 @@ impl ChallengesOverviewView {
         ]
     }
 
+    // Keyed by how rare a level is (100 - share of players in %), rarer levels stand out
+    fn rarity_scale(&self) -> Vec<(f64, Color)> {
+        vec![
+            (99.0, Color::Rgb(255, 100, 255)),
+            (95.0, Color::Rgb(200, 150, 255)),
+            (80.0, Color::Rgb(100, 200, 255)),
+            (0.0, Color::White),
+        ]
+    }
+
+    fn rarity_cell(&self, percentile: Option<f64>) -> Cell<'static> {
+        match percentile {
+            Some(percentile) => {
+                let percent = percentile * 100.0;
+                let color = eval_color_scale_descending(100.0 - percent, &self.rarity_scale());
+                Cell::from(Span::styled(format!("{:.1}%", percent), Style::default().fg(color)))
+            }
+            None if self.percentiles.is_loading() => Cell::from("..."),
+            None => Cell::from("-"),
+        }
+    }
+
+    /// Share of players that reached the current level of the challenge
+    fn challenge_percentile(&self, challenge: &Challenge) -> Option<f64> {
+        if challenge.current_level == "NONE" {
+            return None;
+        }
+        self.percentiles
+            .get_data()?
+            .get(&challenge.id)?
+            .get(&challenge.current_level)
+            .copied()
+    }
+
+    fn comparison_rows(&self) -> Vec<Row<'static>> {
+        let mut rows = vec![fill_row!(6; Cell::from(Line::from(vec![Span::styled(
+            "━━ FRIENDS ━━",
+            Style::default().fg(Color::LightBlue).add_modifier(Modifier::BOLD),
+        )])))];
+
+        if let Some(error) = self.comparison.error() {
+            let message = format!("Failed to compare with friends: {}", error);
+            rows.push(fill_row!(6; "", Span::styled(message, Style::default().fg(Color::Red))));
+        } else if let Some(totals) = self.comparison.get_data() {
+            for player in totals {
+                let style = if player.is_self {
+                    Style::default().fg(Color::LightYellow).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                if let Some(error) = &player.lookup_error {
+                    let error = Span::styled(error.clone(), Style::default().fg(Color::Red));
+                    rows.push(fill_row!(6; player.name.full(), error).style(style));
+                    continue;
+                }
+                rows.push(
+                    Row::new(vec![
+                        Cell::from(player.name.full()),
+                        Cell::from(player.level.clone()),
+                        Cell::from(""),
+                        Cell::from(""),
+                        Cell::from(format!("{}/{}", player.current_points, player.max_points)),
+                        self.rarity_cell(player.percentile),
+                    ])
+                    .style(style),
+                );
+            }
+        } else {
+            rows.push(fill_row!(6; "", Span::styled("Loading...", Style::default().fg(Color::Gray))));
+        }
+
+        rows.push(empty_row!(6));
+        rows
+    }
+
     fn header_with_sorting(&self) -> Row<'static> {
         match self.sorting_state {
-            0 => header_row!("Challenge", "Description", "Mode↓", "Points↑", "Progress↓"),
-            1 => header_row!("Challenge", "Description", "Mode", "Points↓", "Progress"),
-            2 => header_row!("Challenge↑", "Description", "Mode", "Points", "Progress"),
-            3 => header_row!("Challenge", "Description↑", "Mode", "Points", "Progress"),
+            0 => header_row!("Challenge", "Description", "Mode↓", "Points↑", "Progress↓", "Top"),
+            1 => header_row!("Challenge", "Description", "Mode", "Points↓", "Progress", "Top"),
+            2 => header_row!("Challenge↑", "Description", "Mode", "Points", "Progress", "Top"),
+            3 => header_row!("Challenge", "Description↑", "Mode", "Points", "Progress", "Top"),
             _ => unreachable!(),
         }
     }
@@ -131,6 +217,9 @@ impl RenderableView for ChallengesOverviewView {
     }
 
     fn update(&mut self, _controller: &Controller, keys: &[KeyCode]) {
+        self.percentiles.try_update();
+        self.comparison.try_update();
+
         if keys.contains(&KeyCode::Char('s')) {
             self.sorting_state = (self.sorting_state + 1) % 4;
 
@@ -175,13 +264,13 @@ impl RenderableView for ChallengesOverviewView {
             return Ok(());
         }
 
-        // Build rows for the table
-        let mut rows = Vec::new();
+        // Build rows for the table, starting with the comparison with friends
+        let mut rows = self.comparison_rows();
 
         for (category, challenges) in &self.categories {
             // Add category header row spanning multiple columns
             rows.push(
-                fill_row!(5; Cell::from(Line::from(vec![Span::styled(
+                fill_row!(6; Cell::from(Line::from(vec![Span::styled(
                     format!("━━ {} ━━", category),
                     Style::default().fg(Color::LightBlue).add_modifier(Modifier::BOLD),
                 )])))
@@ -206,7 +295,7 @@ impl RenderableView for ChallengesOverviewView {
 
                 // Calculate available width for description
                 // Total terminal width minus: Columns + borders/padding
-                let desc_width = rc.area.width.saturating_sub(32 + 6 + 6 + 12 + 13).max(40) as usize;
+                let desc_width = rc.area.width.saturating_sub(32 + 6 + 6 + 12 + 7 + 15).max(40) as usize;
                 let truncated_desc = Self::truncate_with_ellipsis(&challenge.description, desc_width);
 
                 rows.push(Row::new(vec![
@@ -221,11 +310,12 @@ impl RenderableView for ChallengesOverviewView {
                         format!("{:.0}/{:.0}", challenge.current_value, challenge.threshold_value),
                         Style::default().fg(progress_color),
                     )),
+                    self.rarity_cell(self.challenge_percentile(challenge)),
                 ]));
             }
 
             // Empty row after each category
-            rows.push(empty_row!(5));
+            rows.push(empty_row!(6));
         }
 
         // Skip rows based on scroll offset for manual scrolling